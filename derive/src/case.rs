@@ -0,0 +1,48 @@
+//! Case conversion for generated environment variable names.
+
+/// Convert an identifier written in `camelCase`, `PascalCase` or `snake_case` into
+/// `SCREAMING_SNAKE_CASE`, inserting underscores at case boundaries. Runs of capitals are treated
+/// as a single acronym, so `HTTPServer` becomes `HTTP_SERVER` and not `H_T_T_P_SERVER`.
+pub(crate) fn screaming_snake_case(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::with_capacity(input.len() + 4);
+    for (index, &current) in chars.iter().enumerate() {
+        if current.is_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_is_lower = chars.get(index + 1).is_some_and(|c| c.is_lowercase());
+            let boundary = previous.is_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_uppercase() && next_is_lower);
+            if boundary && !output.ends_with('_') {
+                output.push('_');
+            }
+        }
+        output.extend(current.to_uppercase());
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::screaming_snake_case;
+
+    #[test]
+    fn camel_case() {
+        assert_eq!(screaming_snake_case("listenPort"), "LISTEN_PORT");
+        assert_eq!(screaming_snake_case("maxBodySize"), "MAX_BODY_SIZE");
+    }
+
+    #[test]
+    fn snake_case() {
+        assert_eq!(screaming_snake_case("listen_port"), "LISTEN_PORT");
+        assert_eq!(screaming_snake_case("port"), "PORT");
+        assert_eq!(screaming_snake_case("listen_Port"), "LISTEN_PORT");
+    }
+
+    #[test]
+    fn pascal_case() {
+        assert_eq!(screaming_snake_case("ListenPort"), "LISTEN_PORT");
+        assert_eq!(screaming_snake_case("HTTPServer"), "HTTP_SERVER");
+        assert_eq!(screaming_snake_case("Ipv6Address"), "IPV6_ADDRESS");
+    }
+}
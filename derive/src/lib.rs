@@ -2,15 +2,41 @@ use proc_macro::TokenStream;
 use proc_macro_error2::proc_macro_error;
 use proc_macro_error2::{OptionExt, ResultExt};
 use quote::ToTokens;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::HashMap;
 use syn::{
-    punctuated::Punctuated, token::Comma, Attribute, DeriveInput, Field, Generics, Ident, Meta,
+    ext::IdentExt, punctuated::Punctuated, token::Comma, Attribute, DeriveInput, Field, Generics,
+    Ident, Meta,
 };
 
+mod case;
+
 #[proc_macro_error]
 #[proc_macro_derive(
     HasPartial,
-    attributes(partial_derives, partial_rename, env_source, env, partial_only)
+    attributes(
+        partial_derives,
+        partial_rename,
+        env_source,
+        env,
+        env_parse_with,
+        env_from_inner,
+        env_empty_is_unset,
+        env_parse_duration,
+        env_parse_bytesize,
+        env_separator,
+        env_file_fallback,
+        partial_only,
+        partial_nullable,
+        partial_deny_unknown,
+        partial_error,
+        partial_builder,
+        partial_validate,
+        partial_default,
+        partial_flatten,
+        partial_merge,
+        partial_raw,
+        partial_skip
+    )
 )]
 pub fn has_partial(input: TokenStream) -> TokenStream {
     let DeriveInput {
@@ -24,6 +50,7 @@ pub fn has_partial(input: TokenStream) -> TokenStream {
     // TODO: panic on generics
 
     let partial_ident = partial_struct_name(&ident, &attrs);
+    let error_ty = partial_error_type(&attrs);
 
     match vis {
         syn::Visibility::Public(_) => {}
@@ -66,9 +93,244 @@ pub fn has_partial(input: TokenStream) -> TokenStream {
         }
     };
 
+    // A wrapped `Option` is not recognised as optional, so the field would be required, but could
+    // be specified as `None`, which is almost certainly not what was meant.
+    for field in fields.iter() {
+        if let Some(wrapper) = wrapped_option(&field.ty) {
+            proc_macro_error2::emit_error!(
+                field.ty, "Options wrapped in `{}` are not supported.", wrapper;
+                help = "Change the type of `{}` to `Option<{}<T>>`, or to `{}<T>` if the field is required", field.ident.to_token_stream(), wrapper, wrapper
+            );
+        }
+    }
+
+    // The constructor takes the fields in declaration order. A field behind `#[cfg(...)]` cannot
+    // be passed conditionally.
+    let builder: Option<syn::Expr> = partial_builder(&attrs).map(|constructor| {
+        let args: Vec<&Ident> = fields
+            .iter()
+            .filter_map(|field| {
+                if let Some(cfg) = cfg_attrs(&field.attrs).first() {
+                    proc_macro_error2::emit_error!(
+                        cfg, "Fields behind `#[cfg]` cannot be passed to the `partial_builder` constructor.";
+                        help = "Remove `#[partial_builder]`, or make `{}` unconditional", field.ident.to_token_stream()
+                    );
+                }
+                field.ident.as_ref()
+            })
+            .collect();
+        syn::parse_quote! { #constructor(#(#args),*) }
+    });
+
+    // Skipped fields are not part of the partial, and are only filled in by `build`.
+    let (skipped_fields, fields): (Punctuated<Field, Comma>, Punctuated<Field, Comma>) = fields
+        .into_iter()
+        .partition(|field| find_attr(&field.attrs, "partial_skip").is_some());
+    for field in skipped_fields.iter() {
+        for name in [
+            "partial_nullable",
+            "partial_validate",
+            "partial_default",
+            "partial_flatten",
+            "partial_merge",
+            "partial_raw",
+            "env",
+        ] {
+            if let Some(attr) = find_attr(&field.attrs, name) {
+                proc_macro_error2::emit_error!(
+                    attr, "`#[{}]` cannot be applied to a skipped field.", name;
+                    help = "Remove `#[partial_skip]` to read `{}` from the layers", field.ident.to_token_stream()
+                );
+            }
+        }
+    }
+    let skipped_values: Vec<syn::Stmt> = skipped_fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let value = skip_default(field);
+            Some(cfg_gated(
+                &field.attrs,
+                syn::parse_quote! {
+                    let #ident = #value;
+                },
+            ))
+        })
+        .collect();
+
+    let first_env = fields
+        .iter()
+        .find_map(|field| find_attr(&field.attrs, "env"))
+        .map(|attr| syn::spanned::Spanned::span(attr));
+
+    // A required field that file layers skip and that has no default can only be provided by a
+    // hand-written source, which cannot be seen from here. The warning can be silenced with
+    // `#[allow(deprecated)]` on the structure.
+    let unsatisfiable: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .filter(|field| {
+            !is_option(&field.ty) && find_attr(&field.attrs, "partial_default").is_none()
+        })
+        .filter_map(serde_skip)
+        .map(|attr| {
+            let span = syn::spanned::Spanned::span(attr);
+            quote::quote_spanned! { span => ::partial_config::__private::never_set(); }
+        })
+        .collect();
+    let unsatisfiable_probe = (!unsatisfiable.is_empty()).then(|| {
+        let lints = attrs.iter().filter(|attr| attr.path().is_ident("allow"));
+        quote::quote! {
+            #(#lints)*
+            const _: () = {
+                #[allow(dead_code)]
+                fn probe() {
+                    #(#unsatisfiable)*
+                }
+            };
+        }
+    });
+
+    // The compiler reports a field that is not `Copy` at the derive, without naming the field.
+    if partial_derives_include(&attrs, "Copy") {
+        for field in fields.iter() {
+            if let Some(owner) = owning_type(&field.ty) {
+                proc_macro_error2::emit_error!(
+                    field.ty, "The partial cannot derive `Copy`, because `{}` holds a `{}`.", field.ident.to_token_stream(), owner;
+                    help = "Remove `Copy` from `#[partial_derives]`, or use a `Copy` type for `{}`", field.ident.to_token_stream()
+                );
+            }
+        }
+    }
+
+    // Rendering individual fields requires them to be `Debug`, which is only known for certain if
+    // the partial derives `Debug` and has no type parameters.
+    let renderable =
+        partial_derives_include(&attrs, "Debug") && generics.type_params().next().is_none();
+    let describe = renderable.then(|| impl_describe(&fields));
+    let comparable = renderable.then(|| {
+        let conflicts = impl_conflicts(&fields);
+        let changes = impl_changes(&fields);
+        quote::quote! {
+            #conflicts
+            #changes
+        }
+    });
+    let render_effective = renderable.then(|| impl_render_effective(&fields));
+    let interpolate = cfg!(feature = "interpolate").then(|| impl_interpolate(&fields));
+    let describe = Some(quote::quote! {
+        #describe
+        #interpolate
+    });
+    let diff = renderable.then(|| {
+        let diff = impl_diff(&fields);
+        let set_fields = impl_set_fields(&fields);
+        quote::quote! {
+            #diff
+            #set_fields
+        }
+    });
+
+    let validations = field_validations(&fields);
+    let defaults = impl_defaults(&fields);
+    let setters = impl_setters(&fields);
+
+    let field_names: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            Some(quote::quote! { #(#cfgs)* stringify!(#ident) })
+        })
+        .collect();
+    // The fields that are reported as missing if no layer specifies them. Nested fields report
+    // their own fields instead.
+    let required: Vec<&Field> = fields
+        .iter()
+        .filter(|field| {
+            !is_option(&field.ty)
+                && find_attr(&field.attrs, "partial_flatten").is_none()
+                && find_attr(&field.attrs, "partial_merge").is_none()
+                && find_attr(&field.attrs, "partial_raw").is_none()
+        })
+        .collect();
+    let required_field_names: Vec<proc_macro2::TokenStream> = required
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            Some(quote::quote! { #(#cfgs)* stringify!(#ident) })
+        })
+        .collect();
+    // Every field of the partial is an `Option`, so a derived JSON Schema would not require any of
+    // them. The fields that `build` requires are listed explicitly instead.
+    let schema_required: Option<Attribute> =
+        (partial_derives_include(&attrs, "JsonSchema") && !required.is_empty()).then(|| {
+            let names = required
+                .iter()
+                .filter_map(|field| field.ident.as_ref())
+                .map(|ident| syn::LitStr::new(&ident.unraw().to_string(), ident.span()));
+            syn::parse_quote! {
+                #[schemars(extend("required" = [#(#names),*]))]
+            }
+        });
+    let field_names = quote::quote! {
+        /// The names of all fields, in declaration order.
+        pub const FIELD_NAMES: &'static [&'static str] = &[#(#field_names),*];
+
+        /// The names of the fields that must be specified in some layer for
+        /// [`::partial_config::Partial::build`] to succeed, in declaration order.
+        pub const REQUIRED_FIELD_NAMES: &'static [&'static str] =
+            &[#(#required_field_names),*];
+    };
+
+    let (nested_fields, fields): (Punctuated<Field, Comma>, Punctuated<Field, Comma>) =
+        fields.into_iter().partition(|field| {
+            find_attr(&field.attrs, "partial_flatten").is_some()
+                || merge_key(field).is_some()
+                || find_attr(&field.attrs, "partial_raw").is_some()
+        });
+
+    for field in nested_fields.iter() {
+        if merge_key(field).is_some() {
+            if !is_vec(&field.ty) {
+                proc_macro_error2::emit_error!(
+                    field.ty, "Only `Vec` fields can be merged by key.";
+                    help = "Change the type of `{}` to a `Vec` of a type that derives `HasPartial`", field.ident.to_token_stream()
+                );
+            }
+        } else if is_option(&field.ty) && find_attr(&field.attrs, "partial_raw").is_none() {
+            proc_macro_error2::emit_error!(
+                field.ty, "Flattened fields cannot be optional.";
+                help = "Make the fields of `{}` optional instead", field.ident.to_token_stream()
+            );
+        }
+        for name in [
+            "partial_nullable",
+            "partial_validate",
+            "partial_default",
+            "env",
+        ] {
+            if let Some(attr) = find_attr(&field.attrs, name) {
+                proc_macro_error2::emit_error!(
+                    attr, "`#[{}]` cannot be applied to a flattened field.", name;
+                    help = "Apply it to the fields of the flattened structure instead"
+                );
+            }
+        }
+    }
+
     let (optional_fields, required_fields): (Punctuated<Field, Comma>, Punctuated<Field, Comma>) =
         fields.into_iter().partition(|field| is_option(&field.ty));
 
+    for field in required_fields.iter() {
+        if let Some(attr) = find_attr(&field.attrs, "partial_nullable") {
+            proc_macro_error2::emit_error!(
+                attr, "Only `Option` fields can be nullable.";
+                help = "Change the type of `{}` to an `Option`, or remove `#[partial_nullable]`", field.ident.to_token_stream()
+            );
+        }
+    }
+
     let required_fields: Punctuated<Field, Comma> = required_fields
         .into_iter()
         .map(|field| {
@@ -78,9 +340,107 @@ pub fn has_partial(input: TokenStream) -> TokenStream {
         })
         .collect();
 
+    // Nullable fields carry an extra layer of `Option`, so that a layer can explicitly set the
+    // field to `None` (outer `Some`) as opposed to not mentioning it (outer `None`).
+    let optional_fields: Punctuated<Field, Comma> = optional_fields
+        .into_iter()
+        .map(|field| {
+            if find_attr(&field.attrs, "partial_nullable").is_some() {
+                let ty = field.ty;
+                let ty: syn::Type = syn::parse_quote! { Option<#ty>};
+                Field { ty, ..field }
+            } else {
+                field
+            }
+        })
+        .collect();
+
+    let deserializable = partial_derives_include(&attrs, "Deserialize");
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let comparable = comparable.map(|comparable| {
+        let allow = allow_lints();
+        quote::quote! {
+            #allow
+            impl #impl_generics ::partial_config::Comparable for #partial_ident #ty_generics #where_clause {
+                #comparable
+            }
+        }
+    });
+
     let impl_has_partial = quote::quote! {
-        impl #generics ::partial_config::HasPartial for #ident #generics {
-            type Partial = #partial_ident #generics;
+        impl #impl_generics ::partial_config::HasPartial for #ident #ty_generics #where_clause {
+            type Partial = #partial_ident #ty_generics;
+        }
+    };
+    let render_effective = render_effective.map(|render_effective| {
+        let allow = allow_lints();
+        quote::quote! {
+            #[automatically_derived]
+            #allow
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #render_effective
+            }
+        }
+    });
+
+    // Lets the partial be consumed by code that is generic over `TryFrom`, e.g. `.try_into()`.
+    let impl_try_from = quote::quote! {
+        impl #impl_generics ::core::convert::TryFrom<#partial_ident #ty_generics> for #ident #ty_generics #where_clause {
+            type Error = <#partial_ident #ty_generics as ::partial_config::Partial>::Error;
+
+            fn try_from(partial: #partial_ident #ty_generics) -> ::core::result::Result<Self, Self::Error> {
+                ::partial_config::Partial::build(partial)
+            }
+        }
+    };
+
+    // Every field of the target is specified in the layer, so that it can serve as the lowest
+    // layer, e.g. for `build_with_defaults`.
+    let from_target: Vec<proc_macro2::TokenStream> = optional_fields
+        .iter()
+        .map(|field| {
+            let ident = &field.ident;
+            if find_attr(&field.attrs, "partial_nullable").is_some() {
+                (field, syn::parse_quote! { Some(target.#ident) })
+            } else {
+                (field, syn::parse_quote! { target.#ident })
+            }
+        })
+        .chain(required_fields.iter().map(|field| {
+            let ident = &field.ident;
+            (field, syn::parse_quote! { Some(target.#ident) })
+        }))
+        .chain(nested_fields.iter().map(|field| {
+            let ident = &field.ident;
+            if merge_key(field).is_some() && is_vec(&field.ty) {
+                (
+                    field,
+                    syn::parse_quote! {
+                        target.#ident.into_iter().map(::core::convert::From::from).collect()
+                    },
+                )
+            } else {
+                (
+                    field,
+                    syn::parse_quote! { ::core::convert::From::from(target.#ident) },
+                )
+            }
+        }))
+        .filter_map(|(field, value): (&Field, syn::Expr)| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            Some(quote::quote! { #(#cfgs)* #ident: #value })
+        })
+        .collect();
+    let impl_from_target = quote::quote! {
+        impl #impl_generics ::core::convert::From<#ident #ty_generics> for #partial_ident #ty_generics #where_clause {
+            fn from(target: #ident #ty_generics) -> Self {
+                Self {
+                    #(#from_target,)*
+                }
+            }
         }
     };
 
@@ -90,18 +450,89 @@ pub fn has_partial(input: TokenStream) -> TokenStream {
         &partial_ident,
         &required_fields,
         &optional_fields,
+        &nested_fields,
+        &error_ty,
+        &validations,
+        builder,
+        &skipped_fields,
+        &skipped_values,
+        describe,
+        partial_derives_include(&attrs, "Debug"),
     )
     .unwrap();
 
+    // A flattened field holds the partial of the sub-structure, which is a nested table in file
+    // layers, and is merged and built field by field. A field merged by key holds the partials of
+    // its entries instead. A raw field is a partial already, and is kept as it is.
+    let nested_fields: Punctuated<Field, Comma> = nested_fields
+        .into_iter()
+        .map(|mut field| {
+            let merged = merge_key(&field).is_some() && is_vec(&field.ty);
+            let ty = field.ty;
+            field.ty = if find_attr(&field.attrs, "partial_raw").is_some() {
+                ty
+            } else if merged {
+                let entry = extract_option_generic(&ty);
+                syn::parse_quote! { ::partial_config::__private::Vec<<#entry as ::partial_config::HasPartial>::Partial> }
+            } else {
+                syn::parse_quote! { <#ty as ::partial_config::HasPartial>::Partial }
+            };
+            if deserializable {
+                field.attrs.push(syn::parse_quote! { #[serde(default)] });
+            }
+            field
+        })
+        .collect();
+
+    // A field that the layer does not specify is left out rather than serialized as `null`,
+    // which is how an explicit `None` of a nullable field is written.
+    let serializable = partial_derives_include(&attrs, "Serialize");
     let all_fields: Punctuated<Field, Comma> = optional_fields
         .iter()
-        .cloned()
-        .chain(required_fields.iter().cloned())
-        .map(|field| Field {
-            attrs: field
+        .chain(required_fields.iter())
+        .map(|field| {
+            let mut field = field.clone();
+            if serializable {
+                field.attrs.push(syn::parse_quote! {
+                    #[serde(skip_serializing_if = "::core::option::Option::is_none")]
+                });
+            }
+            field
+        })
+        .chain(nested_fields.iter().cloned())
+        .map(|field| {
+            let nullable = find_attr(&field.attrs, "partial_nullable").is_some();
+            let unit = unit_parser(&field.attrs);
+            for (name, feature, enabled) in [
+                ("env_parse_duration", "humantime", cfg!(feature = "humantime")),
+                ("env_parse_bytesize", "bytesize", cfg!(feature = "bytesize")),
+            ] {
+                if let Some(attr) = find_attr(&field.attrs, name).filter(|_| !enabled) {
+                    proc_macro_error2::emit_error!(
+                        attr, "`#[{}]` requires the `{}` feature of `partial_config`.", name, feature;
+                        help = "Enable the `{}` feature in your `Cargo.toml`", feature
+                    );
+                }
+            }
+            let mut attrs: Vec<Attribute> = field
                 .attrs
                 .into_iter()
-                .filter(|attr| !attr.path().is_ident("env"))
+                .filter(|attr| {
+                    !attr.path().is_ident("env")
+                        && !attr.path().is_ident("env_parse_with")
+                        && !attr.path().is_ident("env_from_inner")
+                        && !attr.path().is_ident("env_empty_is_unset")
+                        && !attr.path().is_ident("env_parse_duration")
+                        && !attr.path().is_ident("env_parse_bytesize")
+                        && !attr.path().is_ident("env_separator")
+                        && !attr.path().is_ident("env_file_fallback")
+                        && !attr.path().is_ident("partial_nullable")
+                        && !attr.path().is_ident("partial_validate")
+                        && !attr.path().is_ident("partial_default")
+                        && !attr.path().is_ident("partial_flatten")
+                        && !attr.path().is_ident("partial_raw")
+                        && !attr.path().is_ident("partial_merge")
+                })
                 .map(|attr| {
                     if attr.path().is_ident("partial_only") {
                         let contents: syn::Meta = attr
@@ -114,46 +545,169 @@ pub fn has_partial(input: TokenStream) -> TokenStream {
                         attr
                     }
                 })
-                .collect(),
-            ..field
+                .collect();
+            if nullable && unit.is_some() {
+                proc_macro_error2::emit_error!(
+                    field.ty, "Fields with units cannot be nullable.";
+                    help = "Remove `#[partial_nullable]` from `{}`", field.ident.to_token_stream()
+                );
+            }
+            if let Some(unit) = unit.filter(|_| deserializable) {
+                let deserialize_with = format!("::partial_config::units::deserialize_{unit}");
+                attrs.push(syn::parse_quote! {
+                    #[serde(default, deserialize_with = #deserialize_with)]
+                });
+            }
+            if nullable && deserializable {
+                attrs.push(syn::parse_quote! {
+                    #[serde(default, deserialize_with = "::partial_config::serde_support::deserialize_nullable")]
+                });
+            }
+            Field { attrs, ..field }
         })
         .collect();
 
     // TODO: Forward all other derives unless otherwise specified.
     // Do not remove serde unless required to
-    let derives: Vec<Attribute> = attribute_assign(&attrs);
+    let mut derives: Vec<Attribute> = attribute_assign(&attrs);
+    derives.extend(schema_required);
+
+    // The `#[env]` attributes are only read by `EnvSourced`, which cannot be seen from here.
+    // `EnvSourced` cannot be derived for generic structures, which are therefore not probed.
+    let env_probe = first_env
+        .filter(|_| cfg!(feature = "std") && generics.params.is_empty())
+        .map(|span| {
+            let check = quote::quote_spanned! { span => check };
+            quote::quote! {
+                const _: () = {
+                    #[allow(unused_imports)]
+                    use ::partial_config::__private::NotEnvSourced as _;
 
+                    #[allow(dead_code)]
+                    fn probe() {
+                        (&::partial_config::__private::EnvProbe::<#ident>(::core::marker::PhantomData)).#check();
+                    }
+                };
+            }
+        });
+
+    let allow = allow_lints();
     let output = quote::quote! {
         #(#derives)*
-        pub struct #partial_ident #generics {
+        #allow
+        pub struct #partial_ident #generics #where_clause {
             #all_fields
         }
 
         #[automatically_derived]
+        #allow
         #impl_partial
 
         #[automatically_derived]
+        #allow
         #impl_has_partial
+
+        #[automatically_derived]
+        #allow
+        #impl_try_from
+
+        #render_effective
+
+        #[automatically_derived]
+        #allow
+        #impl_from_target
+
+        #allow
+        impl #impl_generics #partial_ident #ty_generics #where_clause {
+            /// Override the fields of `self` with the fields specified in `other`. This is
+            /// [`::partial_config::Partial::override_with`], available without importing the trait.
+            pub fn merged_with(self, other: Self) -> Self {
+                <Self as ::partial_config::Partial>::override_with(self, other)
+            }
+
+            #defaults
+
+            #setters
+
+            #field_names
+
+            #diff
+        }
+
+        #comparable
+
+        #env_probe
+        #unsatisfiable_probe
     };
     TokenStream::from(output)
 }
 
 fn partial_struct_name(ident: &Ident, attrs: &Vec<Attribute>) -> Ident {
-    let mut ident = quote::format_ident!("Partial{}", ident);
+    let mut partial_ident = quote::format_ident!("Partial{}", ident);
+    reject_duplicates(attrs, "partial_rename");
     for attr in attrs {
         if attr.path().is_ident("partial_rename") {
-            let identifier: Ident = attr
+            reject_rename_path(attr, "partial structure");
+            let identifier: Ident = match attr.parse_args() {
+                Ok(identifier) => identifier,
+                Err(_) => {
+                    let tokens = attr
+                        .meta
+                        .require_list()
+                        .map(|list| list.tokens.clone())
+                        .unwrap_or_default();
+                    proc_macro_error2::abort!(
+                        attr, "`{}` is not a valid name for the partial structure.", tokens;
+                        help = "Identifiers cannot start with a digit, contain punctuation, or be a keyword, e.g. use `#[partial_rename(Partial{})]`", ident
+                    );
+                }
+            };
+            if identifier == *ident {
+                proc_macro_error2::abort!(
+                    identifier, "The partial structure cannot have the same name as `{}`.", ident;
+                    help = "Pick a distinct name, e.g. `#[partial_rename(Partial{})]`", ident
+                );
+            }
+            partial_ident = identifier;
+        }
+    }
+    partial_ident
+}
+
+/// The associated `Error` of the generated `Partial` implementation, which is
+/// `partial_config::Error` unless overridden with `#[partial_error(path::to::Error)]`. A custom
+/// error type must implement `From<partial_config::Error>`, which is used to report missing
+/// fields.
+fn partial_error_type(attrs: &[Attribute]) -> syn::Type {
+    let mut error_ty = syn::parse_quote! { ::partial_config::Error };
+    reject_duplicates(attrs, "partial_error");
+    for attr in attrs {
+        if attr.path().is_ident("partial_error") {
+            error_ty = attr
                 .parse_args()
-                .expect_or_abort("Failed to parse partial_rename identifier");
-            ident = identifier;
+                .expect_or_abort("Failed to parse partial_error type");
         }
     }
-    ident
+    error_ty
+}
+
+/// The constructor given by `#[partial_builder(path::to::constructor)]`, which `build` calls with
+/// the fields in declaration order instead of constructing the target with a struct literal, so
+/// that the target can have private fields.
+fn partial_builder(attrs: &[Attribute]) -> Option<syn::Path> {
+    reject_duplicates(attrs, "partial_builder");
+    find_attr(attrs, "partial_builder").map(|attr| {
+        attr.parse_args()
+            .expect_or_abort("Failed to parse the partial_builder constructor")
+    })
 }
 
 fn attribute_assign(attrs: &Vec<Attribute>) -> Vec<Attribute> {
     let mut derives: Punctuated<syn::Path, Comma> = Punctuated::new();
     let mut out_attrs: Vec<Attribute> = Vec::new();
+    let mut deny_unknown = false;
+    let serde_derived = partial_derives_include(attrs, "Deserialize")
+        || partial_derives_include(attrs, "Serialize");
     for attr in attrs {
         if attr.path().is_ident("partial_derives") {
             let nested = attr
@@ -174,6 +728,27 @@ fn attribute_assign(attrs: &Vec<Attribute>) -> Vec<Attribute> {
             out_attrs.push(syn::parse_quote! {
                 #[#contents]
             })
+        } else if attr.path().is_ident("partial_deny_unknown") {
+            deny_unknown = true;
+        } else if attr.path().is_ident("serde") && serde_derived {
+            // The conversions name the original structure, which the partial cannot be converted
+            // through, but the naming of the keys, e.g. `rename_all`, applies to both.
+            let nested = attr
+                .parse_args_with(Punctuated::<Meta, syn::Token![,]>::parse_terminated)
+                .expect_or_abort("Invalid specification for `serde`");
+            let forwarded: Punctuated<Meta, Comma> = nested
+                .into_iter()
+                .filter(|item| {
+                    !["from", "try_from", "into", "remote"]
+                        .iter()
+                        .any(|name| item.path().is_ident(name))
+                })
+                .collect();
+            if !forwarded.is_empty() {
+                out_attrs.push(syn::parse_quote! {
+                    #[serde(#forwarded)]
+                });
+            }
         }
     }
 
@@ -181,9 +756,16 @@ fn attribute_assign(attrs: &Vec<Attribute>) -> Vec<Attribute> {
     if !derives.iter().any(|thing| thing.is_ident("Default")) {
         derives.push(syn::parse_quote! {Default});
     }
-    vec![syn::parse_quote! {
+    let mut output: Vec<Attribute> = vec![syn::parse_quote! {
         #[derive(#derives)]
-    }]
+    }];
+    output.extend(out_attrs);
+    if deny_unknown {
+        output.push(syn::parse_quote! {
+            #[serde(deny_unknown_fields)]
+        });
+    }
+    output
 }
 
 fn impl_partial(
@@ -192,93 +774,311 @@ fn impl_partial(
     partial_ident: &Ident,
     required_fields: &Punctuated<Field, Comma>,
     optional_fields: &Punctuated<Field, Comma>,
+    nested_fields: &Punctuated<Field, Comma>,
+    error_ty: &syn::Type,
+    validations: &[syn::Stmt],
+    builder: Option<syn::Expr>,
+    skipped_fields: &Punctuated<Field, Comma>,
+    skipped_values: &[syn::Stmt],
+    describe: Option<proc_macro2::TokenStream>,
+    debug: bool,
 ) -> Result<proc_macro2::TokenStream, &'static str> {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     let error: syn::Expr = syn::parse_quote! {
-        ::core::result::Result::Err(::partial_config::Error::MissingFields {
+        ::core::result::Result::Err(::core::convert::From::from(::partial_config::Error::MissingFields {
             required_fields: missing_fields
-        })
+        }))
     };
 
-    let opt_fields: Punctuated<Ident, Comma> = optional_fields
+    let required_fields_count = required_fields.len();
+    // The partial is only logged in full if it derives `Debug`. Adding `Debug` to its derives
+    // would compile `?self` in every case, but could print secrets that the user left out of it
+    // on purpose, so other partials only log the number of fields.
+    let assembling_config: syn::Stmt =
+        assembling_config(required_fields_count, optional_fields.len(), debug);
+
+    let req_field_expr: Punctuated<syn::Stmt, syn::token::Semi> = required_fields
         .iter()
-        .cloned()
-        .filter_map(|field| field.ident)
+        .filter_map(|field| Some((field, field.ident.clone()?, extract_option_generic(&field.ty))))
+        .map(|(field, ident, ty)| -> syn::Stmt {
+            let env_vars = env_candidates(&field.attrs);
+            let file_key = file_key(field);
+            cfg_gated(&field.attrs, syn::parse_quote! {
+                if self.#ident.is_none() {
+                    missing_fields.push(
+                        ::partial_config::MissingField::with_type(stringify!(#ident), stringify!(#ty))
+                            .settable_by(&[#(#env_vars),*], #file_key)
+                    );
+                }
+            })
+        })
         .collect();
 
-    let req_fields: Punctuated<Ident, Comma> = required_fields
+    // The missing fields of a flattened structure, or of the entries of a field merged by key, are
+    // reported together with those of the parent. Any other error, e.g. an invalid field, is
+    // returned as it is, without the missing fields of the parent, which are not all known yet.
+    let flat_field_expr: Punctuated<syn::Stmt, syn::token::Semi> = nested_fields
         .iter()
-        .cloned()
-        .filter_map(|field| field.ident)
+        .filter_map(|field| Some((field, field.ident.clone()?, merge_key(field))))
+        .map(|(field, ident, key)| -> syn::Stmt {
+            if find_attr(&field.attrs, "partial_raw").is_some() {
+                return cfg_gated(&field.attrs, syn::parse_quote! {
+                    let #ident = self.#ident;
+                });
+            }
+            if key.is_some() {
+                return cfg_gated(&field.attrs, syn::parse_quote! {
+                    let #ident = {
+                        let mut entries = ::partial_config::__private::Vec::with_capacity(self.#ident.len());
+                        let mut complete = true;
+                        for (index, entry) in self.#ident.into_iter().enumerate() {
+                            match ::partial_config::Partial::build(entry) {
+                                ::core::result::Result::Ok(value) => entries.push(value),
+                                ::core::result::Result::Err(err) => {
+                                    complete = false;
+                                    match ::core::convert::Into::<::partial_config::Error>::into(err) {
+                                        ::partial_config::Error::MissingFields { required_fields } => {
+                                            let parent = ::partial_config::__private::format!("{}[{}]", stringify!(#ident), index);
+                                            missing_fields.extend(required_fields.into_iter().map(|field| field.nested(&parent)));
+                                        }
+                                        err => return ::core::result::Result::Err(::core::convert::From::from(err)),
+                                    }
+                                }
+                            }
+                        }
+                        complete.then_some(entries)
+                    };
+                });
+            }
+            let file_key = file_key(field);
+            let env_prefix = format!("{}_", case::screaming_snake_case(&ident.unraw().to_string()));
+            cfg_gated(&field.attrs, syn::parse_quote! {
+                let #ident = match ::partial_config::Partial::build(self.#ident) {
+                    ::core::result::Result::Ok(value) => Some(value),
+                    ::core::result::Result::Err(err) => match ::core::convert::Into::<::partial_config::Error>::into(err) {
+                        ::partial_config::Error::MissingFields { required_fields } => {
+                            missing_fields.extend(required_fields.into_iter().map(|field| field.flattened(stringify!(#ident), #file_key, #env_prefix)));
+                            None
+                        }
+                        err => return ::core::result::Result::Err(::core::convert::From::from(err)),
+                    },
+                };
+            })
+        })
         .collect();
 
-    let assembling_config: syn::Stmt = assembling_config(req_fields.len(), opt_fields.len());
+    // The target is only constructed if every required field is present, so that no placeholder
+    // values need to be produced for the missing ones. Fields behind `#[cfg(...)]` cannot be
+    // matched in a tuple pattern, so they are unwrapped one by one instead.
+    let (req_idents, req_values, gated_values) = required_fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.clone()?;
+            Some((field, syn::parse_quote! { self.#ident }, ident))
+        })
+        .chain(
+            nested_fields
+                .iter()
+                .filter(|field| find_attr(&field.attrs, "partial_raw").is_none())
+                .filter_map(|field| {
+                    let ident = field.ident.clone()?;
+                    Some((field, syn::parse_quote! { #ident }, ident))
+                }),
+        )
+        .fold(
+            (Vec::new(), Vec::new(), Vec::new()),
+            |(mut idents, mut values, mut gated),
+             (field, value, ident): (&Field, syn::Expr, Ident)| {
+                if cfg_attrs(&field.attrs).is_empty() {
+                    idents.push(ident);
+                    values.push(value);
+                } else {
+                    gated.push(cfg_gated(
+                        &field.attrs,
+                        syn::parse_quote! {
+                            let #ident = match #value {
+                                Some(value) => value,
+                                None => return #error,
+                            };
+                        },
+                    ));
+                }
+                (idents, values, gated)
+            },
+        );
+    let incomplete_arm: Option<syn::Arm> = (!req_idents.is_empty()).then(|| {
+        syn::parse_quote! {
+            _ => #error,
+        }
+    });
+
+    // Invalid fields are reported together with the missing ones, so the latter are included in
+    // the `FieldIssues` error if there are any invalid fields.
+    // Without required or nested fields nothing can be missing, so the missing fields are not
+    // collected at all, and the target is constructed directly.
+    let all_optional = required_fields.is_empty() && nested_fields.is_empty();
+    let collect_missing: Option<syn::Stmt> = (!all_optional).then(|| {
+        syn::parse_quote! {
+            let mut missing_fields: ::partial_config::__private::Vec<::partial_config::MissingField<'static>> =
+                ::partial_config::__private::Vec::with_capacity(#required_fields_count);
+        }
+    });
+    let issues: syn::Expr = if all_optional {
+        syn::parse_quote! { invalid_fields }
+    } else {
+        syn::parse_quote! {
+            missing_fields
+                .into_iter()
+                .map(::partial_config::FieldIssue::Missing)
+                .chain(invalid_fields)
+                .collect()
+        }
+    };
+    let invalid_check: Option<proc_macro2::TokenStream> = (!validations.is_empty()).then(|| {
+        quote::quote! {
+            let mut invalid_fields: ::partial_config::__private::Vec<::partial_config::FieldIssue> = ::partial_config::__private::Vec::new();
+            #(#validations)*
+            if !invalid_fields.is_empty() {
+                let issues = #issues;
+                return ::core::result::Result::Err(::core::convert::From::from(
+                    ::partial_config::Error::FieldIssues { issues }
+                ));
+            }
+        }
+    });
 
-    let req_field_expr: Punctuated<syn::Stmt, syn::token::Semi> = req_fields
+    let opt_field_expr: Punctuated<syn::Stmt, syn::token::Semi> = optional_fields
         .iter()
-        .cloned()
-        .map(|ident| -> syn::Stmt {
-            syn::parse_quote! {
-                let #ident = match self.#ident {
-                    Some(value) => value,
-                    None => {
-                        missing_fields.push(::partial_config::MissingField(stringify!(#ident)));
-                        Default::default()
+        .filter_map(|field: &Field| {
+            let nullable = find_attr(&field.attrs, "partial_nullable").is_some();
+            field.ident.as_ref().map(|ident| -> syn::Stmt {
+                // TODO: add explicit fallback
+                let stmt = if nullable {
+                    syn::parse_quote! {
+                        let #ident = self.#ident.flatten();
+                    }
+                } else {
+                    syn::parse_quote! {
+                        let #ident = self.#ident;
                     }
                 };
-            }
+                cfg_gated(&field.attrs, stmt)
+            })
         })
         .collect();
 
-    let opt_field_expr: Punctuated<syn::Stmt, syn::token::Semi> = optional_fields
+    let override_expr: Punctuated<syn::Stmt, syn::token::Semi> = optional_fields
         .iter()
-        .cloned()
-        .filter_map(|field: Field| {
-            field.ident.map(|ident| -> syn::Stmt {
-                // TODO: add explicit fallback
-                syn::parse_quote! {
-                    let #ident = self.#ident;
-                }
-            })
+        .chain(required_fields.iter())
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                let #ident = other.#ident.or(self.#ident);
+            }))
         })
+        .chain(
+            nested_fields
+                .iter()
+                .filter_map(|field| Some((field, field.ident.clone()?, merge_key(field))))
+                .map(|(field, ident, key)| -> syn::Stmt {
+                    let stmt = match key {
+                        // Entries with matching keys are merged field by field, the rest are
+                        // appended in order.
+                        Some(key) => syn::parse_quote! {
+                            let #ident = {
+                                let mut entries = self.#ident;
+                                for entry in other.#ident {
+                                    match entries.iter().position(|existing| {
+                                        existing.#key.is_some() && existing.#key == entry.#key
+                                    }) {
+                                        Some(index) => {
+                                            let existing = ::core::mem::take(&mut entries[index]);
+                                            entries[index] =
+                                                ::partial_config::Partial::override_with(existing, entry);
+                                        }
+                                        None => entries.push(entry),
+                                    }
+                                }
+                                entries
+                            };
+                        },
+                        None => syn::parse_quote! {
+                            let #ident = ::partial_config::Partial::override_with(self.#ident, other.#ident);
+                        },
+                    };
+                    cfg_gated(&field.attrs, stmt)
+                }),
+        )
         .collect();
 
-    let all_fields: Punctuated<Ident, Comma> = opt_fields
-        .into_iter()
-        .chain(req_fields.into_iter())
+    let all_fields: Punctuated<proc_macro2::TokenStream, Comma> = optional_fields
+        .iter()
+        .chain(required_fields.iter())
+        .chain(nested_fields.iter())
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            Some(quote::quote! { #(#cfgs)* #ident })
+        })
         .collect();
 
-    let override_expr: Punctuated<syn::Stmt, syn::token::Semi> = all_fields
+    let skipped_idents: Vec<proc_macro2::TokenStream> = skipped_fields
         .iter()
-        .cloned()
-        .map(|ident: Ident| -> syn::Stmt {
-            syn::parse_quote! {
-                let #ident = other.#ident.or(self.#ident);
-            }
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            Some(quote::quote! { #(#cfgs)* #ident, })
         })
         .collect();
+    let target: syn::Expr = builder.unwrap_or_else(|| {
+        syn::parse_quote! {
+            Self::Target {
+                #(#skipped_idents)*
+                #all_fields
+            }
+        }
+    });
+    let target: syn::Expr = if skipped_values.is_empty() {
+        target
+    } else {
+        syn::parse_quote! {
+            {
+                #(#skipped_values)*
+                #target
+            }
+        }
+    };
+    let construct = if all_optional {
+        quote::quote! {
+            Ok(#target)
+        }
+    } else {
+        quote::quote! {
+            match ( #(#req_values,)* ) {
+                ( #(Some(#req_idents),)* ) => Ok(#target),
+                #incomplete_arm
+            }
+        }
+    };
 
     Ok(quote::quote! {
-        impl #generics ::partial_config::Partial for #partial_ident #generics {
-            type Target = #ident #generics;
+        impl #impl_generics ::partial_config::Partial for #partial_ident #ty_generics #where_clause {
+            type Target = #ident #ty_generics;
 
-            type Error = ::partial_config::Error;
+            type Error = #error_ty;
 
             fn build(self) -> Result<Self::Target, Self::Error> {
-                let mut missing_fields = ::std::vec::Vec::new();
+                #collect_missing
                 #assembling_config;
 
                 #req_field_expr
+                #flat_field_expr
+                #invalid_check
                 #opt_field_expr
+                #(#gated_values)*
 
-                if !missing_fields.is_empty() {
-                    #error
-                } else {
-                    Ok(
-                        Self::Target {
-                            #all_fields
-                        }
-                    )
-                }
+                #construct
             }
 
             fn override_with(self, other: Self) -> Self {
@@ -288,26 +1088,660 @@ fn impl_partial(
                 }
 
             }
+
+            #describe
         }
     })
 }
 
-fn is_option(ty: &syn::Type) -> bool {
-    match ty {
-        syn::Type::Path(path) => path
-            .path
-            .segments
-            .last()
-            .map(|segment| segment.ident == "Option")
-            .unwrap_or(false),
-        _ => false,
-    }
+/// Statements checking the fields annotated with `#[partial_validate(path::to::validator)]`, where
+/// the validator is a `fn(&T) -> Result<(), E>` with `E: Display`, pushing to `invalid_fields`.
+fn field_validations(fields: &Punctuated<Field, Comma>) -> Vec<syn::Stmt> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let validator: syn::Path = find_attr(&field.attrs, "partial_validate")?
+                .parse_args()
+                .expect_or_abort("Failed to parse the partial_validate function path");
+            let value: syn::Expr = if find_attr(&field.attrs, "partial_nullable").is_some() {
+                syn::parse_quote! { self.#ident.as_ref().and_then(|value| value.as_ref()) }
+            } else {
+                syn::parse_quote! { self.#ident.as_ref() }
+            };
+            Some(cfg_gated(
+                &field.attrs,
+                syn::parse_quote! {
+                    if let Some(value) = #value {
+                        if let Err(reason) = #validator(value) {
+                            invalid_fields.push(::partial_config::FieldIssue::Invalid {
+                                field: stringify!(#ident),
+                                reason: ::partial_config::__private::ToString::to_string(&reason),
+                            });
+                        }
+                    }
+                },
+            ))
+        })
+        .collect()
 }
 
-fn extract_option_generic(ty: &syn::Type) -> syn::Type {
-    match ty {
-        syn::Type::Path(path) => path
-            .path
+/// The `defaults` constructor, which specifies the fields annotated with
+/// `#[partial_default(expr)]`, and the defaults of flattened structures.
+fn impl_defaults(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let values: Vec<syn::FieldValue> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            let ty = &field.ty;
+            let value: syn::Expr = if find_attr(&field.attrs, "partial_flatten").is_some() {
+                syn::parse_quote! { <#ty as ::partial_config::HasPartial>::Partial::defaults() }
+            } else if let Some(attr) = find_attr(&field.attrs, "partial_default") {
+                let default: syn::Expr = attr
+                    .parse_args()
+                    .expect_or_abort("Failed to parse the partial_default expression");
+                if find_attr(&field.attrs, "partial_nullable").is_some() {
+                    syn::parse_quote! { Some(Some(#default)) }
+                } else {
+                    syn::parse_quote! { Some(#default) }
+                }
+            } else {
+                syn::parse_quote! { ::core::default::Default::default() }
+            };
+            Some(syn::parse_quote! {
+                #(#cfgs)*
+                #ident: #value
+            })
+        })
+        .collect();
+
+    quote::quote! {
+        /// A layer that specifies the values given by `#[partial_default(...)]`, to be used as
+        /// the base layer that files and environment variables override. The remaining fields
+        /// are unspecified, as are all fields of [`Default::default`].
+        pub fn defaults() -> Self {
+            Self {
+                #(#values,)*
+            }
+        }
+    }
+}
+
+/// A `with_field` method per field, which specifies the field in this layer. Optional fields take
+/// the value inside the `Option`, and flattened fields take the partial of the sub-structure.
+fn impl_setters(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let setters: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            let cfgs = cfg_attrs(&field.attrs);
+            let setter = quote::format_ident!("with_{}", ident.unraw());
+            let doc = format!(" Specify `{}` in this layer.", ident.unraw());
+            let ty = &field.ty;
+            let (param, value): (syn::Type, syn::Expr) = if merge_key(field).is_some() && is_vec(ty) {
+                let entry = extract_option_generic(ty);
+                (
+                    syn::parse_quote! { ::partial_config::__private::Vec<<#entry as ::partial_config::HasPartial>::Partial> },
+                    syn::parse_quote! { value },
+                )
+            } else if find_attr(&field.attrs, "partial_raw").is_some() {
+                (ty.clone(), syn::parse_quote! { value })
+            } else if holds_partial(field) {
+                (
+                    syn::parse_quote! { <#ty as ::partial_config::HasPartial>::Partial },
+                    syn::parse_quote! { value },
+                )
+            } else if is_option(ty) && find_attr(&field.attrs, "partial_nullable").is_some() {
+                (extract_option_generic(ty), syn::parse_quote! { Some(Some(value)) })
+            } else if is_option(ty) {
+                (extract_option_generic(ty), syn::parse_quote! { Some(value) })
+            } else {
+                (ty.clone(), syn::parse_quote! { Some(value) })
+            };
+            Some(quote::quote! {
+                #(#cfgs)*
+                #[doc = #doc]
+                pub fn #setter(mut self, value: #param) -> Self {
+                    self.#ident = #value;
+                    self
+                }
+            })
+        })
+        .collect();
+
+    quote::quote! {
+        #(#setters)*
+    }
+}
+
+fn impl_diff(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let changes: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if holds_partial(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    {
+                        let from = ::partial_config::__private::format!("{:?}", self.#ident);
+                        let to = ::partial_config::__private::format!("{:?}", other.#ident);
+                        if from != to {
+                            changes.push(::partial_config::FieldChange {
+                                field: stringify!(#ident),
+                                from: Some(from),
+                                to: Some(to),
+                            });
+                        }
+                    }
+                }));
+            }
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                if self.#ident.is_some() || other.#ident.is_some() {
+                    let from = self.#ident.as_ref().map(|value| ::partial_config::__private::format!("{:?}", value));
+                    let to = other.#ident.as_ref().map(|value| ::partial_config::__private::format!("{:?}", value));
+                    if from != to {
+                        changes.push(::partial_config::FieldChange {
+                            field: stringify!(#ident),
+                            from,
+                            to,
+                        });
+                    }
+                }
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        /// List the fields whose [`Debug`] representation differs between `self` and `other`,
+        /// ignoring the fields that are unspecified in both. Useful for previewing what applying
+        /// `other` as a layer would change.
+        pub fn diff(&self, other: &Self) -> ::partial_config::__private::Vec<::partial_config::FieldChange> {
+            let mut changes = ::partial_config::__private::Vec::new();
+            #(#changes)*
+            changes
+        }
+    }
+}
+
+fn impl_set_fields(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let entries: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if holds_partial(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    fields.push((stringify!(#ident), ::partial_config::__private::format!("{:?}", self.#ident)));
+                }));
+            }
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                if let Some(value) = &self.#ident {
+                    fields.push((stringify!(#ident), ::partial_config::__private::format!("{:?}", value)));
+                }
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        /// The fields that are specified in this layer, with their values rendered with
+        /// [`Debug`], in declaration order. Useful for logging the effective configuration at
+        /// startup. Nested structures are rendered as a whole.
+        pub fn set_fields(&self) -> impl ::core::iter::Iterator<Item = (&'static str, ::partial_config::__private::String)> {
+            let mut fields = ::partial_config::__private::Vec::new();
+            #(#entries)*
+            fields.into_iter()
+        }
+    }
+}
+
+fn impl_changes(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let checks: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if let Some(key) = merge_key(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    for entry in &other.#ident {
+                        match self.#ident.iter().find(|existing| {
+                            existing.#key.is_some() && existing.#key == entry.#key
+                        }) {
+                            Some(existing) => changes.extend(::partial_config::Comparable::changes(existing, entry)),
+                            None => changes.push(::partial_config::FieldChange {
+                                field: stringify!(#ident),
+                                from: None,
+                                to: Some(::partial_config::__private::format!("{:?}", entry)),
+                            }),
+                        }
+                    }
+                }));
+            }
+            if find_attr(&field.attrs, "partial_flatten").is_some()
+                || find_attr(&field.attrs, "partial_raw").is_some()
+            {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    changes.extend(::partial_config::Comparable::changes(&self.#ident, &other.#ident));
+                }));
+            }
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                if let Some(to) = &other.#ident {
+                    let from = self.#ident.as_ref().map(|value| ::partial_config::__private::format!("{:?}", value));
+                    let to = ::partial_config::__private::format!("{:?}", to);
+                    if from.as_ref() != Some(&to) {
+                        changes.push(::partial_config::FieldChange {
+                            field: stringify!(#ident),
+                            from,
+                            to: Some(to),
+                        });
+                    }
+                }
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        fn changes(&self, other: &Self) -> ::partial_config::__private::Vec<::partial_config::FieldChange> {
+            let mut changes = ::partial_config::__private::Vec::new();
+            #(#checks)*
+            changes
+        }
+    }
+}
+
+fn impl_conflicts(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let checks: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if let Some(key) = merge_key(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    for entry in &other.#ident {
+                        if let Some(existing) = self.#ident.iter().find(|existing| {
+                            existing.#key.is_some() && existing.#key == entry.#key
+                        }) {
+                            changes.extend(::partial_config::Comparable::conflicts(existing, entry));
+                        }
+                    }
+                }));
+            }
+            if find_attr(&field.attrs, "partial_flatten").is_some()
+                || find_attr(&field.attrs, "partial_raw").is_some()
+            {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    changes.extend(::partial_config::Comparable::conflicts(&self.#ident, &other.#ident));
+                }));
+            }
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                if let (Some(from), Some(to)) = (&self.#ident, &other.#ident) {
+                    let from = ::partial_config::__private::format!("{:?}", from);
+                    let to = ::partial_config::__private::format!("{:?}", to);
+                    if from != to {
+                        changes.push(::partial_config::FieldChange {
+                            field: stringify!(#ident),
+                            from: Some(from),
+                            to: Some(to),
+                        });
+                    }
+                }
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        fn conflicts(&self, other: &Self) -> ::partial_config::__private::Vec<::partial_config::FieldChange> {
+            let mut changes = ::partial_config::__private::Vec::new();
+            #(#checks)*
+            changes
+        }
+    }
+}
+
+fn impl_describe(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let lines: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if holds_partial(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    lines.push(::partial_config::__private::format!("{} = {:?}", stringify!(#ident), self.#ident));
+                }));
+            }
+            let absent = if is_option(&field.ty) {
+                "<default>"
+            } else {
+                "<missing>"
+            };
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                lines.push(match &self.#ident {
+                    Some(value) => ::partial_config::__private::format!("{} = {:?}", stringify!(#ident), value),
+                    None => ::partial_config::__private::format!("{} = {}", stringify!(#ident), #absent),
+                });
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        fn describe(&self) -> ::partial_config::__private::String
+        where
+            Self: ::core::fmt::Debug,
+        {
+            let mut lines: ::partial_config::__private::Vec<::partial_config::__private::String> = ::partial_config::__private::Vec::new();
+            #(#lines)*
+            lines.join("\n")
+        }
+    }
+}
+
+/// Render the fields of the target, descending into the structures that hold partials, whose
+/// targets are rendered by their own `render_effective`.
+fn impl_render_effective(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let lines: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if merge_key(field).is_some() && is_vec(&field.ty) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    for (index, entry) in self.#ident.iter().enumerate() {
+                        lines.extend(entry.render_effective().lines().map(|line| {
+                            ::partial_config::__private::format!("{}[{}].{}", stringify!(#ident), index, line)
+                        }));
+                    }
+                }));
+            }
+            if find_attr(&field.attrs, "partial_flatten").is_some() {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    lines.extend(self.#ident.render_effective().lines().map(|line| {
+                        ::partial_config::__private::format!("{}.{}", stringify!(#ident), line)
+                    }));
+                }));
+            }
+            if is_option(&field.ty) && find_attr(&field.attrs, "partial_raw").is_none() {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    lines.push(match &self.#ident {
+                        Some(value) => ::partial_config::__private::format!("{} = {:?}", stringify!(#ident), value),
+                        None => ::partial_config::__private::format!("{} = None", stringify!(#ident)),
+                    });
+                }));
+            }
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                lines.push(::partial_config::__private::format!("{} = {:?}", stringify!(#ident), self.#ident));
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        /// Render the configuration one `field = value` line per field, in declaration order,
+        /// e.g. for golden-file tests of the whole layering pipeline. Fields of flattened
+        /// structures are prefixed with the path to them, as in `tls.cert = "server.pem"`, and
+        /// optional fields are rendered without `Some`.
+        pub fn render_effective(&self) -> ::partial_config::__private::String {
+            let mut lines: ::partial_config::__private::Vec<::partial_config::__private::String> = ::partial_config::__private::Vec::new();
+            #(#lines)*
+            lines.join("\n")
+        }
+    }
+}
+
+/// Expand `${VAR}` in the fields of type `String` or `Option<String>`, and in flattened
+/// structures, qualifying the reported field with the name of the flattened field.
+fn impl_interpolate(fields: &Punctuated<Field, Comma>) -> proc_macro2::TokenStream {
+    let expansions: Vec<syn::Stmt> = fields
+        .iter()
+        .filter_map(|field| {
+            let ident = field.ident.as_ref()?;
+            if merge_key(field).is_some() && is_vec(&field.ty) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    for entry in &mut self.#ident {
+                        ::partial_config::interpolate::within(
+                            stringify!(#ident),
+                            ::partial_config::Partial::interpolate(entry, lookup),
+                        )?;
+                    }
+                }));
+            }
+            if holds_partial(field) {
+                return Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                    ::partial_config::interpolate::within(
+                        stringify!(#ident),
+                        ::partial_config::Partial::interpolate(&mut self.#ident, lookup),
+                    )?;
+                }));
+            }
+            let optional = is_option(&field.ty);
+            let inner = if optional {
+                extract_option_generic(&field.ty)
+            } else {
+                field.ty.clone()
+            };
+            if !matches!(&inner, syn::Type::Path(path) if path.path.is_ident("String")) {
+                return None;
+            }
+            let pattern: syn::Pat = if optional && find_attr(&field.attrs, "partial_nullable").is_some() {
+                syn::parse_quote! { Some(Some(value)) }
+            } else {
+                syn::parse_quote! { Some(value) }
+            };
+            Some(cfg_gated(&field.attrs, syn::parse_quote! {
+                if let #pattern = &mut self.#ident {
+                    *value = ::partial_config::interpolate::expand(value, stringify!(#ident), lookup)?;
+                }
+            }))
+        })
+        .collect();
+
+    quote::quote! {
+        fn interpolate(
+            &mut self,
+            lookup: &dyn Fn(&str) -> ::core::option::Option<::partial_config::__private::String>,
+        ) -> ::core::result::Result<(), ::partial_config::Error> {
+            #(#expansions)*
+            Ok(())
+        }
+    }
+}
+
+/// Whether the field holds a partial, or several, rather than an `Option` of a value, i.e. it is
+/// flattened, merged by key, or raw.
+fn holds_partial(field: &Field) -> bool {
+    find_attr(&field.attrs, "partial_flatten").is_some()
+        || merge_key(field).is_some()
+        || find_attr(&field.attrs, "partial_raw").is_some()
+}
+
+/// The key field of `#[partial_merge(by_key = "name")]`, by which the entries of a `Vec` field
+/// are matched when merging layers.
+fn merge_key(field: &Field) -> Option<Ident> {
+    let attr = find_attr(&field.attrs, "partial_merge")?;
+    let mut key = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("by_key") {
+            let name: syn::LitStr = meta.value()?.parse()?;
+            key = Some(name.parse::<Ident>()?);
+            Ok(())
+        } else {
+            Err(meta.error("Expected `by_key = \"field_name\"`"))
+        }
+    })
+    .expect_or_abort("Invalid specification for `partial_merge`");
+    Some(key.expect_or_abort("`partial_merge` requires a key, e.g. `by_key = \"name\"`"))
+}
+
+/// The value that `build` gives a field marked `#[partial_skip]`, which is `expr` in
+/// `#[partial_skip(default = expr)]`, or else `Default::default()`.
+fn skip_default(field: &Field) -> syn::Expr {
+    let mut value = None;
+    if let Some(attr) =
+        find_attr(&field.attrs, "partial_skip").filter(|attr| matches!(attr.meta, Meta::List(_)))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                value = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("Expected `default = value`"))
+            }
+        })
+        .expect_or_abort("Invalid specification for `partial_skip`");
+    }
+    value.unwrap_or_else(|| syn::parse_quote! { ::core::default::Default::default() })
+}
+
+/// Lints that the generated code may trip, depending on the shape of the structure, e.g.
+/// `unused_mut` for the list of set fields of a structure without fields. Crates that deny warnings
+/// or enable stricter Clippy groups should not have to fix code that they did not write.
+fn allow_lints() -> Attribute {
+    syn::parse_quote! {
+        #[allow(unused_mut, clippy::all, clippy::pedantic, clippy::nursery)]
+    }
+}
+
+/// The `#[partial_only(serde(skip))]` or `#[partial_only(serde(skip_deserializing))]` attribute of
+/// a field, which file layers can therefore never set.
+fn serde_skip(field: &Field) -> Option<&Attribute> {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("partial_only"))
+        .find(|attr| {
+            let Ok(syn::Meta::List(list)) = attr.parse_args::<syn::Meta>() else {
+                return false;
+            };
+            list.path.is_ident("serde")
+                && list
+                    .parse_args_with(Punctuated::<syn::Meta, Comma>::parse_terminated)
+                    .is_ok_and(|metas| {
+                        metas.iter().any(|meta| {
+                            meta.path().is_ident("skip")
+                                || meta.path().is_ident("skip_deserializing")
+                        })
+                    })
+        })
+}
+
+fn find_attr<'a>(attrs: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+    attrs.iter().find(|attr| attr.path().is_ident(name))
+}
+
+/// The `#[cfg(...)]` attributes of a field, which are repeated wherever the generated code
+/// refers to the field, so that the field is absent from the partial whenever it is absent from
+/// the structure.
+fn cfg_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .cloned()
+        .collect()
+}
+
+/// Apply the `#[cfg(...)]` attributes of a field to a statement referring to it. Bindings are
+/// annotated directly, so that they remain in scope, other statements are wrapped in a block,
+/// since attributes are not allowed on e.g. `if` expressions.
+fn cfg_gated(attrs: &[Attribute], stmt: syn::Stmt) -> syn::Stmt {
+    let cfgs = cfg_attrs(attrs);
+    match stmt {
+        _ if cfgs.is_empty() => stmt,
+        syn::Stmt::Local(mut local) => {
+            local.attrs.splice(0..0, cfgs);
+            syn::Stmt::Local(local)
+        }
+        stmt => syn::parse_quote! {
+            #(#cfgs)*
+            {
+                #stmt
+            }
+        },
+    }
+}
+
+/// Whether a derive macro with the given name, e.g. `Deserialize` for `serde::Deserialize`, is
+/// forwarded to the partial via `#[partial_derives]`.
+fn partial_derives_include(attrs: &[Attribute], name: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("partial_derives"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<syn::Path, Comma>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .any(|path| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == name)
+        })
+}
+
+fn is_vec(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Vec"),
+        _ => false,
+    }
+}
+
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// The smart pointer or cell, e.g. `Box`, if the type is such a wrapper of an `Option`.
+fn wrapped_option(ty: &syn::Type) -> Option<&Ident> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if !["Box", "Rc", "Arc", "Cell", "RefCell", "Mutex", "RwLock"]
+        .iter()
+        .any(|wrapper| segment.ident == wrapper)
+    {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args
+        .iter()
+        .any(|arg| matches!(arg, syn::GenericArgument::Type(inner) if is_option(inner)))
+        .then_some(&segment.ident)
+}
+
+/// The well-known standard type that owns memory, e.g. `String`, and therefore is not `Copy`, if
+/// the type is one, or an `Option` of one.
+fn owning_type(ty: &syn::Type) -> Option<&Ident> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident == "Option" {
+        let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        return args.args.iter().find_map(|arg| match arg {
+            syn::GenericArgument::Type(inner) => owning_type(inner),
+            _ => None,
+        });
+    }
+    [
+        "String", "Vec", "Box", "Rc", "Arc", "Cow", "HashMap", "HashSet", "BTreeMap", "BTreeSet",
+        "VecDeque", "PathBuf", "OsString", "RefCell", "Mutex", "RwLock",
+    ]
+    .iter()
+    .any(|owner| segment.ident == owner)
+    .then_some(&segment.ident)
+}
+
+fn extract_option_generic(ty: &syn::Type) -> syn::Type {
+    match ty {
+        syn::Type::Path(path) => path
+            .path
             .segments
             .last()
             .map(|segment| match &segment.arguments {
@@ -342,26 +1776,82 @@ fn extract_option_generic(ty: &syn::Type) -> syn::Type {
 #[cfg(all(feature = "tracing", feature = "log"))]
 compile_error!("The features \"tracing\" and \"log\" are mutually exclusive. Please either use pure tracing, or enable the \"log\" feature in \"tracing\" and use the \"log\" feature of this crate. ");
 
-fn assembling_config(required_fields_count: usize, optional_fields_count: usize) -> syn::Stmt {
+/// The message logged when a partial is built. The partial itself is only included if it derives
+/// `Debug`, which is not required of it.
+fn assembling_config(
+    required_fields_count: usize,
+    optional_fields_count: usize,
+    debug: bool,
+) -> syn::Stmt {
+    #[cfg(not(feature = "tracing"))]
+    let _ = debug;
     #[cfg(feature = "tracing")]
-    syn::parse_quote! {
-        {
-            ::tracing::info!(?self, "Building configuration {required_fields_count} ({optional_fields_count})", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count);
+    if debug {
+        syn::parse_quote! {
+            {
+                ::tracing::info!(?self, "Building configuration {required_fields_count} ({optional_fields_count})", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count);
+            }
+        }
+    } else {
+        syn::parse_quote! {
+            {
+                ::tracing::info!("Building configuration {required_fields_count} ({optional_fields_count})", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count);
+            }
         }
     }
     #[cfg(feature = "log")]
     syn::parse_quote! {
         ::log::info!("Building configuration. {required_fields_count} ({optional_fields_count}) fields", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count);
     }
-    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    #[cfg(all(feature = "std", not(any(feature = "tracing", feature = "log"))))]
     syn::parse_quote! {
-        println!("Building configuration. {required_fields_count} ({optional_fields_count}) fields", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count);
+        ::partial_config::report::report(::partial_config::report::Level::Info, &::std::format!("Building configuration. {required_fields_count} ({optional_fields_count}) fields", required_fields_count = #required_fields_count, optional_fields_count=#optional_fields_count));
+    }
+    // Without `std` there is nowhere to print to, so nothing is reported.
+    #[cfg(not(any(feature = "std", feature = "tracing", feature = "log")))]
+    {
+        let _ = (required_fields_count, optional_fields_count);
+        syn::parse_quote! { {} }
     }
 }
 
-#[proc_macro_error]
-#[proc_macro_derive(EnvSourced, attributes(env_var_rename, env))]
+#[proc_macro_derive(
+    EnvSourced,
+    attributes(
+        env_var_rename,
+        env_profile_prefix,
+        env,
+        env_parse_with,
+        env_from_inner,
+        env_empty_is_unset,
+        env_parse_duration,
+        env_parse_bytesize,
+        env_separator,
+        env_file_fallback,
+        partial_flatten,
+        partial_merge,
+        partial_raw,
+        partial_skip
+    )
+)]
 pub fn env_sourced(input: TokenStream) -> TokenStream {
+    // The marker is implemented even if the expansion fails, so that the probe of `HasPartial`
+    // does not add a warning to the errors.
+    let marker = syn::parse::<DeriveInput>(input.clone())
+        .ok()
+        .filter(|input| input.generics.params.is_empty())
+        .map(|DeriveInput { ident, .. }| {
+            TokenStream::from(quote::quote! {
+                impl ::partial_config::__private::DerivesEnvSourced for #ident {}
+            })
+        });
+    let mut output = expand_env_sourced(input);
+    output.extend(marker);
+    output
+}
+
+#[proc_macro_error(allow_not_macro)]
+fn expand_env_sourced(input: TokenStream) -> TokenStream {
     let DeriveInput {
         data,
         attrs,
@@ -369,6 +1859,7 @@ pub fn env_sourced(input: TokenStream) -> TokenStream {
         ..
     } = syn::parse_macro_input!(input as DeriveInput);
 
+    let profile = env_profile_prefix(&attrs);
     let out_ident: Ident = env_var_struct_name(attrs);
     let strct = match data {
         syn::Data::Struct(strct) => strct,
@@ -376,8 +1867,13 @@ pub fn env_sourced(input: TokenStream) -> TokenStream {
         syn::Data::Union(_) => panic!("Data unions are not supported"),
     };
 
+    // Skipped fields are not part of the partial, so they are not read either.
     let fields: Punctuated<Field, Comma> = match strct.fields {
-        syn::Fields::Named(fld) => fld.named,
+        syn::Fields::Named(fld) => fld
+            .named
+            .into_iter()
+            .filter(|field| find_attr(&field.attrs, "partial_skip").is_none())
+            .collect(),
         _ => unreachable!(),
     };
 
@@ -386,44 +1882,79 @@ pub fn env_sourced(input: TokenStream) -> TokenStream {
         default_mappings,
     } = env_var_fields(&fields);
 
-    let default_struct = impl_default_env(default_mappings);
+    let default_struct = impl_default_env(default_mappings, &fields);
+    // If the partial does not have exactly the fields of the structure, e.g. because it is
+    // implemented by hand, the struct literal below fails to compile. Spanning it at the structure
+    // makes the error point there, rather than at the derive.
+    let partial_alias = Ident::new("Issue86935Workaround", in_ident.span());
     let impl_source = impl_source(&fields);
+    // The profile is only known at runtime, so it is folded into the prefix before any of the
+    // fields are read.
+    let select_profile: Option<proc_macro2::TokenStream> = profile.map(|profile| {
+        quote::quote! {
+            let prefix = ::partial_config::env::profile_prefix(prefix, stringify!(#profile), lookup)?;
+            let prefix = prefix.as_str();
+        }
+    });
 
+    let allow = allow_lints();
     let output = quote::quote! {
+    #allow
     pub struct #out_ident<'a> {
         #all_fields
     }
 
+    #allow
     impl<'a> ::partial_config::env::EnvSourced<'a> for #in_ident {
         type Source = #out_ident<'a>;
     }
 
+    #allow
     impl<'a> #out_ident<'a> {
         pub const fn new() -> Self {
             #default_struct
         }
     }
 
+    #allow
     impl<'a> Default for #out_ident<'a> {
         fn default() -> Self {
             #default_struct
         }
     }
 
+    #allow
+    impl<'a> ::partial_config::env::PrefixedSource<#in_ident> for #out_ident<'a> {
+        fn to_partial_prefixed(
+            self,
+            prefix: &str,
+            lookup: &dyn Fn(&str) -> Result<String, ::std::env::VarError>,
+        ) -> Result<<#in_ident as ::partial_config::HasPartial>::Partial, Self::Error> {
+            pub type #partial_alias = <#in_ident as ::partial_config::HasPartial>::Partial;
+            #select_profile
+
+            Ok(#partial_alias {
+                #impl_source
+            })
+        }
+    }
+
+    #allow
     impl<'a> ::partial_config::Source<#in_ident> for #out_ident<'a> {
         type Error = ::partial_config::Error;
 
         fn to_partial(self) -> Result<<#in_ident as ::partial_config::HasPartial>::Partial, Self::Error> {
-            pub type Issue86935Workaround = <#in_ident as ::partial_config::HasPartial>::Partial;
-
-            Ok(Issue86935Workaround {
-                #impl_source
-            })
+            let snapshot = ::partial_config::env::EnvSnapshot::capture();
+            ::partial_config::env::PrefixedSource::to_partial_prefixed(self, "", &|key| snapshot.var(key))
         }
 
         fn name(&self) -> String {
             "Environment Variables".to_owned()
         }
+
+        fn source_kind(&self) -> ::std::borrow::Cow<'static, str> {
+            ::std::borrow::Cow::Borrowed("Environment Variables")
+        }
     }
     };
     TokenStream::from(output)
@@ -431,7 +1962,7 @@ pub fn env_sourced(input: TokenStream) -> TokenStream {
 
 struct EnvVarFieldsResult {
     fields: Punctuated<Field, Comma>,
-    default_mappings: HashMap<Ident, BTreeSet<Ident>>,
+    default_mappings: HashMap<Ident, Vec<String>>,
 }
 
 fn is_string(ty: &syn::Type) -> bool {
@@ -442,52 +1973,312 @@ fn is_string(ty: &syn::Type) -> bool {
     }
 }
 
+/// The unit named in `#[env_parse_duration]` or `#[env_parse_bytesize]`, which selects the parser
+/// and deserializer of the same name in `partial_config::units`.
+fn unit_parser(attrs: &[Attribute]) -> Option<&'static str> {
+    if find_attr(attrs, "env_parse_duration").is_some() {
+        Some("duration")
+    } else if find_attr(attrs, "env_parse_bytesize").is_some() {
+        Some("bytesize")
+    } else {
+        None
+    }
+}
+
+/// Whether the type is a `Cow<'_, str>`, which does not implement `FromStr`, but can hold the
+/// `String` read from the environment.
+fn is_cow_str(ty: &syn::Type) -> bool {
+    let syn::Type::Path(pth) = ty else {
+        return false;
+    };
+    let Some(segment) = pth.path.segments.last() else {
+        return false;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return false;
+    };
+    segment.ident == "Cow"
+        && args.args.iter().any(|arg| {
+            matches!(arg, syn::GenericArgument::Type(syn::Type::Path(inner)) if inner.path.is_ident("str"))
+        })
+}
+
+/// Whether the type can hold arbitrary, possibly non-Unicode, environment variable values.
+fn is_os_string(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(pth) => pth
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "PathBuf" || segment.ident == "OsString"),
+        _ => false,
+    }
+}
+
+/// Whether the type is a primitive integer, for which `FromStr` fails with `ParseIntError`.
+fn is_integer(ty: &syn::Type) -> bool {
+    const INTEGERS: [&str; 12] = [
+        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+    ];
+    match ty {
+        syn::Type::Path(pth) => INTEGERS.iter().any(|integer| pth.path.is_ident(integer)),
+        _ => false,
+    }
+}
+
 fn impl_source(fields: &Punctuated<Field, Comma>) -> Punctuated<syn::FieldValue, Comma> {
     fields
         .iter()
-        .map(|Field { ident, ty, .. }| -> syn::FieldValue {
-            if let Some(ident) = ident {
-                if is_string(&ty) {
-                    syn::parse_quote! {
-                        #ident: ::partial_config::env::extract(&self.#ident)?
+        .map(
+            |Field {
+                 ident, ty, attrs, ..
+             }|
+             -> syn::FieldValue {
+                if let Some(ident) = ident {
+                    // Collections merged by key and raw partials are not read from environment
+                    // variables.
+                    if find_attr(attrs, "partial_merge").is_some() || find_attr(attrs, "partial_raw").is_some() {
+                        return syn::parse_quote! {
+                            #ident: ::core::default::Default::default()
+                        };
                     }
-                } else {
+                    if find_attr(attrs, "partial_flatten").is_some() {
+                        let prefix = format!("{}_", case::screaming_snake_case(&ident.unraw().to_string()));
+                        return syn::parse_quote! {
+                            #ident: ::partial_config::env::PrefixedSource::<#ty>::to_partial_prefixed(
+                                <<#ty as ::partial_config::env::EnvSourced<'a>>::Source as ::core::default::Default>::default(),
+                                &format!("{}{}", prefix, #prefix),
+                                lookup,
+                            )?
+                        };
+                    }
+                    let log_source = log_env_source(ident);
+                    let mut lookup: syn::Expr = syn::parse_quote! { lookup };
+                    if find_attr(attrs, "env_file_fallback").is_some() {
+                        lookup = syn::parse_quote! { &|key: &str| ::partial_config::env::file_fallback(#lookup, key) };
+                    }
+                    if find_attr(attrs, "env_empty_is_unset").is_some() {
+                        lookup = syn::parse_quote! { &|key: &str| ::partial_config::env::non_empty(#lookup, key) };
+                    }
+                    let flag = env_flag(attrs);
+                    if flag.is_some() {
+                        lookup = syn::parse_quote! { &|key: &str| ::partial_config::env::presence(#lookup, key) };
+                    }
+                    let (extract, extract_os) = if env_ordered(attrs) {
+                        (quote::quote! { extract_ordered_prefixed_with_source }, quote::quote! { extract_os_ordered_prefixed_with_source })
+                    } else {
+                        (quote::quote! { extract_prefixed_with_source }, quote::quote! { extract_os_prefixed_with_source })
+                    };
+                    let raw: syn::Expr = syn::parse_quote! {
+                        {
+                            let found = ::partial_config::env::#extract(prefix, &self.#ident, #lookup)?;
+                            #log_source
+                            found.map(|(_, value)| value)
+                        }
+                    };
+                    let parse_with: Option<syn::Path> = find_attr(attrs, "env_parse_with").map(|attr| {
+                        attr.parse_args()
+                            .expect_or_abort("Failed to parse the env_parse_with function path")
+                    }).or_else(|| {
+                        let parser = quote::format_ident!("parse_{}", unit_parser(attrs)?);
+                        Some(syn::parse_quote! { ::partial_config::units::#parser })
+                    });
                     let inner_ty = if is_option(ty) {
                         extract_option_generic(ty)
                     } else {
                         ty.clone()
                     };
-                    syn::parse_quote! {
-                        #ident: ::partial_config::env::extract(&self.#ident)?
-                        .map(|s: String| <#inner_ty as ::core::str::FromStr>::from_str(&s))
-                        .transpose()
-                        .map_err(|e|
-                            ::partial_config::Error::ParseFieldError {
-                                field_name: stringify!(#ident),
-                                field_type: stringify!(#ty),
-                                error_condition: Box::new(e)
-                            })?
+                    // Neither layer of `Option` could be told apart in the environment, so the
+                    // compiler would only report that `Option` is not `FromStr`.
+                    if is_option(&inner_ty) {
+                        proc_macro_error2::abort!(
+                            inner_ty, "Nested options cannot be read from environment variables.";
+                            help = "Change the type of `{}` to `Option<{}>`, and mark it `#[partial_nullable]` if a layer needs to clear it", ident, extract_option_generic(&inner_ty).to_token_stream()
+                        );
+                    }
+                    let value: syn::Expr = if let Some(span) = flag {
+                        if !matches!(&inner_ty, syn::Type::Path(pth) if pth.path.is_ident("bool")) {
+                            proc_macro_error2::emit_error!(
+                                span, "Only `bool` fields can be flags, but `{}` is a `{}`.", ident, inner_ty.to_token_stream();
+                                help = "Remove `flag` to parse the value of the environment variable instead"
+                            );
+                        }
+                        syn::parse_quote! {
+                            #raw.map(|_: String| true)
+                        }
+                    } else if let Some(parser) = parse_with {
+                        syn::parse_quote! {
+                            #raw
+                            .map(|s: String| #parser(&s).map_err(|e|
+                                ::partial_config::Error::ParseFieldError {
+                                    field_name: stringify!(#ident),
+                                    field_type: stringify!(#ty),
+                                    value: s,
+                                    error_condition: ::core::convert::Into::into(e)
+                                }))
+                            .transpose()?
+                        }
+                    } else if let Some(attr) = find_attr(attrs, "env_from_inner") {
+                        // The constructor of the tuple struct determines the inner type to parse.
+                        let constructor = match &inner_ty {
+                            syn::Type::Path(pth)
+                                if pth.qself.is_none()
+                                    && pth.path.segments.iter().all(|segment| segment.arguments.is_none()) =>
+                            {
+                                pth.path.clone()
+                            }
+                            _ => proc_macro_error2::abort!(
+                                attr, "`#[env_from_inner]` requires the field to be a non-generic tuple struct.";
+                                help = "Implement `FromStr` for the type of `{}` instead, or use `#[env_parse_with]`", ident
+                            ),
+                        };
+                        syn::parse_quote! {
+                            #raw
+                            .map(|s: String| ::partial_config::env::from_inner(#constructor, &s).map_err(|e|
+                                ::partial_config::Error::ParseFieldError {
+                                    field_name: stringify!(#ident),
+                                    field_type: stringify!(#ty),
+                                    value: s,
+                                    error_condition: Box::new(e)
+                                }))
+                            .transpose()?
+                        }
+                    } else if is_vec(&inner_ty) {
+                        // Lists are read from a single variable, e.g. `TAGS=a,b,c`.
+                        let separator: syn::LitStr = find_attr(attrs, "env_separator")
+                            .map(|attr| {
+                                attr.parse_args()
+                                    .expect_or_abort("The separator must be a string literal, e.g. `#[env_separator(\";\")]`")
+                            })
+                            .unwrap_or_else(|| syn::LitStr::new(",", ident.span()));
+                        let element = extract_option_generic(&inner_ty);
+                        syn::parse_quote! {
+                            #raw
+                            .map(|s: String| ::partial_config::env::split_list::<#element>(&s, #separator).map_err(|e|
+                                ::partial_config::Error::ParseFieldError {
+                                    field_name: stringify!(#ident),
+                                    field_type: stringify!(#ty),
+                                    value: s,
+                                    error_condition: Box::new(e)
+                                }))
+                            .transpose()?
+                        }
+                    } else if is_string(ty) {
+                        raw
+                    } else if is_cow_str(&inner_ty) {
+                        syn::parse_quote! {
+                            #raw.map(::std::borrow::Cow::Owned)
+                        }
+                    } else if is_os_string(&inner_ty) {
+                        // Paths need not be Unicode, so they are not converted to `String`.
+                        syn::parse_quote! {
+                            {
+                                let found = ::partial_config::env::#extract_os(prefix, &self.#ident, #lookup)?;
+                                #log_source
+                                found.map(|(_, value)| <#inner_ty as ::core::convert::From<::std::ffi::OsString>>::from(value))
+                            }
+                        }
+                    } else {
+                        // Errors that the crate has a dedicated variant for keep their concrete
+                        // type, so that they can be matched on.
+                        let map_err: syn::Expr = if is_integer(&inner_ty) {
+                            syn::parse_quote! {
+                                |error| ::partial_config::Error::ParseIntFieldError {
+                                    field_name: stringify!(#ident),
+                                    field_type: stringify!(#ty),
+                                    value: s,
+                                    error
+                                }
+                            }
+                        } else {
+                            syn::parse_quote! {
+                                |e| ::partial_config::Error::ParseFieldError {
+                                    field_name: stringify!(#ident),
+                                    field_type: stringify!(#ty),
+                                    value: s,
+                                    error_condition: Box::new(e)
+                                }
+                            }
+                        };
+                        syn::parse_quote! {
+                            #raw
+                            .map(|s: String| <#inner_ty as ::core::str::FromStr>::from_str(&s).map_err(#map_err))
+                            .transpose()?
+                        }
+                    };
+                    let value: syn::Expr = match env_default(attrs) {
+                        Some(default) => syn::parse_quote! {
+                            #value.or_else(|| Some(#default))
+                        },
+                        None => value,
+                    };
+                    if find_attr(attrs, "partial_nullable").is_some() {
+                        syn::parse_quote! {
+                            #ident: #value.map(Some)
+                        }
+                    } else {
+                        syn::parse_quote! {
+                            #ident: #value
+                        }
                     }
+                } else {
+                    proc_macro_error2::abort!(ident, "Non-struct like fields are not allowed");
                 }
-            } else {
-                proc_macro_error2::abort!(ident, "Non-struct like fields are not allowed");
-            }
+            },
+        )
+        .zip(fields.iter())
+        .map(|(mut value, field)| {
+            value.attrs = cfg_attrs(&field.attrs);
+            value
         })
         .collect()
 }
 
-fn impl_default_env(default_mappings: HashMap<Ident, BTreeSet<Ident>>) -> syn::ExprStruct {
+/// Record which candidate environment variable supplied the value of the field, if a logging
+/// backend is enabled.
+fn log_env_source(ident: &Ident) -> Option<syn::Stmt> {
+    #[cfg(feature = "tracing")]
+    let stmt = syn::parse_quote! {
+        if let Some((candidate, _)) = &found {
+            ::tracing::debug!("The field `{}` was set via the environment variable {candidate}", stringify!(#ident));
+        }
+    };
+    #[cfg(feature = "log")]
+    let stmt = syn::parse_quote! {
+        if let Some((candidate, _)) = &found {
+            ::log::debug!("The field `{}` was set via the environment variable {candidate}", stringify!(#ident));
+        }
+    };
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    return Some(stmt);
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    {
+        let _ = ident;
+        None
+    }
+}
+
+fn impl_default_env(
+    default_mappings: HashMap<Ident, Vec<String>>,
+    fields: &Punctuated<Field, Comma>,
+) -> syn::ExprStruct {
     let elements: Punctuated<syn::FieldValue, Comma> = default_mappings
         .iter()
         .map(|(field_name, env_var_strings)| -> syn::FieldValue {
+            let cfgs = fields
+                .iter()
+                .find(|field| field.ident.as_ref() == Some(field_name))
+                .map(|field| cfg_attrs(&field.attrs))
+                .unwrap_or_default();
             let env_var_strings: Punctuated<syn::LitStr, Comma> = env_var_strings
                 .iter()
                 .cloned()
-                .map(|ident| -> syn::LitStr {
-                    syn::LitStr::new(&ident.to_string(), proc_macro2::Span::call_site())
+                .map(|name| -> syn::LitStr {
+                    syn::LitStr::new(&name, proc_macro2::Span::call_site())
                 })
                 .collect();
             syn::parse_quote! {
+                #(#cfgs)*
                 #field_name: [#env_var_strings]
             }
         })
@@ -500,41 +2291,151 @@ fn impl_default_env(default_mappings: HashMap<Ident, BTreeSet<Ident>>) -> syn::E
     }
 }
 
+/// One entry of `#[env(...)]`: the name of a candidate environment variable, the candidates in
+/// order of precedence given as `ordered(A, B)`, the value given as `default = value`, or the
+/// `flag` marker. Names that are not valid Rust identifiers, e.g. `my.var` or `MY-VAR`, can be
+/// given as string literals.
+enum EnvArg {
+    Var(String, proc_macro2::Span),
+    Ordered(Vec<(String, proc_macro2::Span)>, proc_macro2::Span),
+    Default(syn::Expr),
+    Flag(proc_macro2::Span),
+}
+
+impl syn::parse::Parse for EnvArg {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::LitStr) {
+            let name: syn::LitStr = input.parse()?;
+            let value = name.value();
+            // Neither can be part of the name of an environment variable on any system.
+            if value.is_empty() || value.contains(['=', '\0']) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "The name of an environment variable must not be empty, nor contain `=` or NUL",
+                ));
+            }
+            return Ok(Self::Var(value, name.span()));
+        }
+        match input.parse::<Meta>()? {
+            Meta::Path(pth) if pth.is_ident("flag") => {
+                Ok(Self::Flag(syn::spanned::Spanned::span(&pth)))
+            }
+            Meta::Path(pth) => {
+                let ident = pth.get_ident().ok_or_else(|| {
+                    syn::Error::new_spanned(&pth, "Must have identifier and not a path")
+                })?;
+                Ok(Self::Var(ident.to_string(), ident.span()))
+            }
+            Meta::NameValue(pair) if pair.path.is_ident("default") => Ok(Self::Default(pair.value)),
+            Meta::List(list) if list.path.is_ident("ordered") => {
+                let vars = list
+                    .parse_args_with(Punctuated::<EnvArg, Comma>::parse_terminated)?
+                    .into_iter()
+                    .map(|arg| match arg {
+                        EnvArg::Var(name, span) => Ok((name, span)),
+                        _ => Err(syn::Error::new_spanned(
+                            &list.tokens,
+                            "Only the names of environment variables can be ordered",
+                        )),
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Ok(Self::Ordered(vars, syn::spanned::Spanned::span(&list)))
+            }
+            item => Err(syn::Error::new_spanned(
+                &item,
+                format!(
+                    "Expected the name of an environment variable, `ordered(...)` or `default = value`, got {}",
+                    item.to_token_stream()
+                ),
+            )),
+        }
+    }
+}
+
+/// The entries of all `#[env(...)]` attributes of a field, in order.
+fn env_args(attrs: &[Attribute]) -> Vec<EnvArg> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("env"))
+        .flat_map(|attr| {
+            attr.parse_args_with(Punctuated::<EnvArg, Comma>::parse_terminated)
+                .unwrap_or_else(|err| {
+                    proc_macro_error2::abort!(
+                        err.span(),
+                        "Invalid specification for the `env` attribute: {}",
+                        err
+                    )
+                })
+        })
+        .collect()
+}
+
 fn env_var_fields(fields: &Punctuated<Field, Comma>) -> EnvVarFieldsResult {
     let mut output = Punctuated::new();
-    let mut default_mappings: HashMap<Ident, BTreeSet<Ident>> = HashMap::new();
-    for field in fields {
-        let mut n = 0_usize;
-        field.attrs.iter().for_each(|attr| {
-            if attr.path().is_ident("env") {
-                let nested = attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated).expect_or_abort("Invalid specification for the `env` attribute");
-                let env_vars: BTreeSet<Ident> = nested.iter().
-                    filter_map(|item| {
-                        match item {
-                            Meta::Path(pth) => Some(pth.get_ident().expect_or_abort("Must have identifier and not a path").clone()),
-                            _ => None
-                        }
-                    })
-                    .collect();
-                n+=env_vars.len();
-                let key = field.ident.clone().expect_or_abort("Identifiers for all fields must be known at this point");
-                default_mappings.entry(key.clone())
-                    .and_modify(|previous| {
-                        if !previous.is_disjoint(&env_vars) {
-                            proc_macro_error2::emit_error!(key, "Environment variable specifications must be disjoint. The field {key} has the following duplicate specifications {:?}",
-                                previous.intersection(&env_vars).map(|ident| ident.to_string()).collect::<Vec<_>>());
-                        }
-                        previous.extend(env_vars.iter().cloned())
-                    })
-                    .or_insert(env_vars);
+    let mut default_mappings: HashMap<Ident, Vec<String>> = HashMap::new();
+    let mut claimed_by: HashMap<String, Ident> = HashMap::new();
+    // Flattened fields are read by the source of the sub-structure, and collections merged by key
+    // and raw partials are not read at all.
+    for field in fields.iter().filter(|field| {
+        find_attr(&field.attrs, "partial_flatten").is_none()
+            && find_attr(&field.attrs, "partial_merge").is_none()
+            && find_attr(&field.attrs, "partial_raw").is_none()
+    }) {
+        let key = field
+            .ident
+            .clone()
+            .expect_or_abort("Identifiers for all fields must be known at this point");
+        let mut env_vars: Vec<String> = Vec::new();
+        let mut aliases = false;
+        let mut ordered = None;
+        let names = env_args(&field.attrs)
+            .into_iter()
+            .flat_map(|arg| match arg {
+                EnvArg::Var(env_var, span) => {
+                    aliases = true;
+                    vec![(env_var, span)]
+                }
+                EnvArg::Ordered(vars, span) => {
+                    ordered = Some(span);
+                    vars
+                }
+                EnvArg::Default(_) | EnvArg::Flag(_) => Vec::new(),
+            });
+        for (env_var, span) in names.collect::<Vec<_>>() {
+            match claimed_by.get(&env_var) {
+                Some(first) if *first != key => {
+                    proc_macro_error2::emit_error!(span, "The environment variable {} is claimed by both `{}` and `{}`", env_var, first, key;
+                        note = first.span() => "`{}` first claims {} here", first, env_var;
+                        help = "Each environment variable may only feed a single field. Remove {} from one of the fields.", env_var)
+                }
+                Some(_) => {}
+                None => {
+                    claimed_by.insert(env_var.clone(), key.clone());
+                }
             }
-        });
+            if env_vars.contains(&env_var) {
+                proc_macro_error2::emit_error!(key, "Environment variable specifications must be disjoint. The field {key} has the following duplicate specifications {:?}",
+                    [env_var]);
+            } else {
+                env_vars.push(env_var);
+            }
+        }
+        if let Some(span) = ordered.filter(|_| aliases) {
+            proc_macro_error2::emit_error!(span, "The environment variables of `{}` must either all be ordered or all be aliases.", key;
+                help = "Move all of the names into `ordered(...)`, or remove `ordered` to require that the variables that are set agree")
+        }
+        let n = env_vars.len();
         if n == 0 {
+            let suggestion = field
+                .ident
+                .as_ref()
+                .map(|ident| case::screaming_snake_case(&ident.unraw().to_string()))
+                .unwrap_or_default();
             proc_macro_error2::emit_error!(field.ident, "At least one `env` directive must be specified";
-                help = "Try using an uppercase version of the field name: {}", field.ident.to_token_stream().to_string().to_uppercase();
+                help = "Try using an uppercase version of the field name: {}", suggestion;
                 note = "It is better to enforce that all env-var deserializeable fields are explicitly set in the code.")
         }
-        // TODO: check uniqueness in leaf nodes
+        default_mappings.insert(key, env_vars);
         // TODO: Check for empty nodes and replace with uppercase
         let ty: syn::Type = syn::parse_quote! {
             [&'a str; #n]
@@ -542,7 +2443,7 @@ fn env_var_fields(fields: &Punctuated<Field, Comma>) -> EnvVarFieldsResult {
 
         output.push(Field {
             ty,
-            attrs: vec![],
+            attrs: cfg_attrs(&field.attrs),
             ..field.clone()
         });
     }
@@ -553,10 +2454,131 @@ fn env_var_fields(fields: &Punctuated<Field, Comma>) -> EnvVarFieldsResult {
     }
 }
 
+/// The value given by `#[env(PORT, default = 8080)]`, which the environment source yields if
+/// none of the candidates are set. String literals are converted with `Into`, so that they can
+/// initialise e.g. `String` and `PathBuf` fields.
+fn env_default(attrs: &[Attribute]) -> Option<syn::Expr> {
+    env_args(attrs)
+        .into_iter()
+        .find_map(|arg| match arg {
+            EnvArg::Default(default) => Some(default),
+            EnvArg::Var(..) | EnvArg::Ordered(..) | EnvArg::Flag(_) => None,
+        })
+        .map(|default| match default {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(_),
+                ..
+            }) => syn::parse_quote! { ::core::convert::Into::into(#default) },
+            default => default,
+        })
+}
+
+/// Whether the candidates of a field are given as `#[env(ordered(SERVICE_PORT, SHARED_PORT))]`,
+/// so that the first one that is set wins, rather than as aliases that must agree.
+fn env_ordered(attrs: &[Attribute]) -> bool {
+    env_args(attrs)
+        .iter()
+        .any(|arg| matches!(arg, EnvArg::Ordered(..)))
+}
+
+/// The span of `flag` in `#[env(DEBUG, flag)]`, which marks a `bool` field that is `true` if
+/// any of the environment variables is set, whatever its value.
+fn env_flag(attrs: &[Attribute]) -> Option<proc_macro2::Span> {
+    env_args(attrs).into_iter().find_map(|arg| match arg {
+        EnvArg::Flag(span) => Some(span),
+        _ => None,
+    })
+}
+
+/// The environment variables named in the `#[env(...)]` attributes of a field, which are reported
+/// for it if it is missing.
+fn env_candidates(attrs: &[Attribute]) -> Vec<String> {
+    env_args(attrs)
+        .into_iter()
+        .flat_map(|arg| match arg {
+            EnvArg::Var(name, _) => vec![name],
+            EnvArg::Ordered(vars, _) => vars.into_iter().map(|(name, _)| name).collect(),
+            EnvArg::Default(_) | EnvArg::Flag(_) => Vec::new(),
+        })
+        .collect()
+}
+
+/// The key of a field in a configuration file, which is the name given in
+/// `#[serde(rename = "...")]`, if any, or else the name of the field.
+fn file_key(field: &Field) -> String {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("serde"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Meta, Comma>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .find_map(|item| match item {
+            Meta::NameValue(pair) if pair.path.is_ident("rename") => match pair.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(name),
+                    ..
+                }) => Some(name.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .or_else(|| field.ident.as_ref().map(|ident| ident.unraw().to_string()))
+        .unwrap_or_default()
+}
+
+/// The environment variable named in `#[env_profile_prefix(CONFIG_PROFILE)]`, whose value selects
+/// the prefix of all other environment variables at runtime.
+fn env_profile_prefix(attrs: &[Attribute]) -> Option<Ident> {
+    find_attr(attrs, "env_profile_prefix").map(|attr| {
+        attr.parse_args()
+            .expect_or_abort("Failed to parse env_profile_prefix identifier. ")
+    })
+}
+
+/// The generated structure is defined next to the annotated one, so it cannot be placed in
+/// another module by renaming it to a path, e.g. `#[partial_rename(config::Partial)]`.
+fn reject_rename_path(attr: &Attribute, what: &str) {
+    let Ok(path) = attr.parse_args::<syn::Path>() else {
+        return;
+    };
+    if path.get_ident().is_none() {
+        let segments: Vec<String> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let name = segments.last().cloned().unwrap_or_default();
+        proc_macro_error2::abort!(
+            path, "The {} is defined next to the annotated structure, so it cannot be named by the path `{}`.", what, segments.join("::");
+            help = "Use `#[{}({})]` instead, and re-export it from the intended module with `pub use`", attr.path().to_token_stream(), name
+        );
+    }
+}
+
+/// Attributes that name a single thing, e.g. `#[partial_rename]`, would otherwise silently take
+/// the last of several values.
+fn reject_duplicates(attrs: &[Attribute], name: &str) {
+    for attr in attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident(name))
+        .skip(1)
+    {
+        proc_macro_error2::emit_error!(
+            attr, "`#[{}]` is specified more than once.", name;
+            help = "Keep only one `#[{}]` attribute", name
+        );
+    }
+}
+
 fn env_var_struct_name(attrs: Vec<Attribute>) -> Ident {
     let mut ident = syn::parse_quote! { EnvVarSource };
+    reject_duplicates(&attrs, "env_var_rename");
     for attr in attrs {
         if attr.path().is_ident("env_var_rename") {
+            reject_rename_path(&attr, "environment variable source");
             let identifier: Ident = attr
                 .parse_args()
                 .expect_or_abort("Failed to parse env_var_rename identifier. ");
@@ -0,0 +1,89 @@
+//! Writing partial layers back into existing configuration files, preserving the comments and
+//! formatting of the original file. This is the counterpart of the read-only
+//! [`Toml`](crate::serde_support::Toml) source, useful for interactive configuration editors.
+
+use std::path::Path;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Writing a configuration file has failed.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FileWriteError {
+    /// Reading or writing the file failed with the provided `io::Error`.
+    Io(std::io::Error),
+    /// The existing file is not valid TOML.
+    Parse(toml_edit::TomlError),
+    /// The partial could not be represented as TOML.
+    Serialize(toml_edit::ser::Error),
+}
+
+impl From<std::io::Error> for FileWriteError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl core::fmt::Display for FileWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "The file system reported the following error {err}"),
+            Self::Parse(err) => write!(f, "Error parsing the existing TOML file {err}"),
+            Self::Serialize(err) => write!(f, "Error converting the configuration to TOML {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FileWriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::Serialize(err) => Some(err),
+        }
+    }
+}
+
+/// Merge the fields that are specified in `partial` into the TOML file at `path`. Fields that are
+/// `None` are left untouched, existing keys keep their position and comments, and new keys are
+/// appended. If the file does not exist, it is created.
+///
+/// # Errors
+///
+/// - If the file exists but cannot be read, or cannot be written.
+/// - If the existing file is not valid TOML.
+/// - If `partial` cannot be represented as TOML.
+pub fn write_partial_toml<P>(path: impl AsRef<Path>, partial: &P) -> Result<(), FileWriteError>
+where
+    P: serde::Serialize,
+{
+    let path = path.as_ref();
+    let mut document: DocumentMut = match std::fs::read_to_string(path) {
+        Ok(contents) => contents.parse().map_err(FileWriteError::Parse)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => DocumentMut::new(),
+        Err(err) => return Err(err.into()),
+    };
+    let update = toml_edit::ser::to_document(partial).map_err(FileWriteError::Serialize)?;
+    merge(document.as_table_mut(), update.as_table());
+    std::fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+fn merge(existing: &mut dyn TableLike, update: &dyn TableLike) {
+    for (key, new) in update.iter() {
+        match existing.get_mut(key) {
+            Some(old) if old.is_table_like() && new.is_table_like() => merge(
+                old.as_table_like_mut().expect("Checked above"),
+                new.as_table_like().expect("Checked above"),
+            ),
+            Some(Item::Value(old)) if new.is_value() => {
+                let decor = old.decor().clone();
+                *old = new.as_value().expect("Checked above").clone();
+                *old.decor_mut() = decor;
+            }
+            Some(old) => *old = new.clone(),
+            None => {
+                existing.insert(key, new.clone());
+            }
+        }
+    }
+}
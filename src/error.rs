@@ -1,19 +1,161 @@
 //! Error types and variants used in this crate. They are provided for reference only, all traits
 //! accept an optional `Error` type which you should make use of if you need customised errors.
 
+use alloc::{borrow::Cow, format, string::String, string::ToString, vec::Vec};
+
 /// A field that is required is not specified in _any_ of the layers. Missing from one layer is not
 /// a hard error, and if you need that, you should consider using a different crate.
 #[derive(Debug)]
-pub struct MissingField<'a>(pub &'a str);
+pub struct MissingField<'a> {
+    /// The name of the field. Fields of nested structures, e.g. those marked `#[partial_flatten]`,
+    /// are prefixed with the path to the structure, as in `tls.cert` or `servers[1].port`.
+    pub path: Cow<'a, str>,
+    /// The type of the field as written in the structure, e.g. `Duration`, or empty if unknown.
+    pub type_name: &'static str,
+    /// The environment variables from which the field could be read, as given in `#[env(...)]`,
+    /// and without any prefix chosen at runtime. Empty if the field is not read from the
+    /// environment.
+    pub env_vars: Vec<String>,
+    /// The key under which the field could be specified in a configuration file, e.g. `tls.cert`.
+    /// It differs from [`Self::path`] if the field is renamed with `#[serde(rename = "...")]`.
+    pub file_key: Cow<'a, str>,
+}
+
+impl<'a> MissingField<'a> {
+    /// A missing field of unknown type.
+    pub const fn new(path: &'a str) -> Self {
+        Self::with_type(path, "")
+    }
+
+    /// A missing field of the type `type_name`.
+    pub const fn with_type(path: &'a str, type_name: &'static str) -> Self {
+        Self {
+            path: Cow::Borrowed(path),
+            type_name,
+            env_vars: Vec::new(),
+            file_key: Cow::Borrowed(path),
+        }
+    }
+
+    /// The same field, which could be set from the environment variables `env_vars`, or in a
+    /// file under `file_key`.
+    pub fn settable_by(self, env_vars: &[&str], file_key: &'a str) -> Self {
+        Self {
+            env_vars: env_vars.iter().map(ToString::to_string).collect(),
+            file_key: Cow::Borrowed(file_key),
+            ..self
+        }
+    }
+
+    /// The same field, found in the nested structure at `parent`. Entries of collections, as in
+    /// `servers[1]`, are not read from the environment, so the environment variables are dropped.
+    pub fn nested(self, parent: &str) -> MissingField<'static> {
+        MissingField {
+            path: Cow::Owned(format!("{parent}.{}", self.path)),
+            type_name: self.type_name,
+            env_vars: Vec::new(),
+            file_key: Cow::Owned(format!("{parent}.{}", self.file_key)),
+        }
+    }
+
+    /// The same field, found in the structure flattened into the field `parent`, which is read
+    /// from the file under `parent_key` and from environment variables starting with
+    /// `env_prefix`.
+    pub fn flattened(
+        self,
+        parent: &str,
+        parent_key: &str,
+        env_prefix: &str,
+    ) -> MissingField<'static> {
+        MissingField {
+            path: Cow::Owned(format!("{parent}.{}", self.path)),
+            type_name: self.type_name,
+            env_vars: self
+                .env_vars
+                .iter()
+                .map(|var| format!("{env_prefix}{var}"))
+                .collect(),
+            file_key: Cow::Owned(format!("{parent_key}.{}", self.file_key)),
+        }
+    }
+}
 
 impl<'a> core::fmt::Display for MissingField<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "The field {} is missing", self.0)
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.type_name.is_empty() {
+            write!(f, "The field {} is missing", self.path)?;
+        } else {
+            write!(
+                f,
+                "The field {} of type `{}` is missing",
+                self.path, self.type_name
+            )?;
+        }
+        if !self.env_vars.is_empty() {
+            write!(
+                f,
+                ", set it with `{}` in a file or one of the environment variables {}",
+                self.file_key,
+                self.env_vars.join(", ")
+            )?;
+        }
+        Ok(())
     }
 }
 
+#[cfg(feature = "std")]
 impl<'a> std::error::Error for MissingField<'a> {}
 
+/// A problem with a single field, found while building the configuration. All of the problems
+/// are collected in one pass, so that they can be reported at once.
+#[derive(Debug)]
+pub enum FieldIssue {
+    /// The field is required, but not specified in any of the layers.
+    Missing(MissingField<'static>),
+    /// The field is specified, but the value was rejected by the validation function given in
+    /// `#[partial_validate]`.
+    Invalid { field: &'static str, reason: String },
+}
+
+impl core::fmt::Display for FieldIssue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FieldIssue::Missing(missing) => write!(f, "{missing}"),
+            FieldIssue::Invalid { field, reason } => {
+                write!(f, "The field {field} is invalid, because {reason}")
+            }
+        }
+    }
+}
+
+/// A field that two layers combined with [`crate::Strict`] specify differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingConflict {
+    /// The name of the field.
+    pub field: &'static str,
+    /// The name of the earlier layer.
+    pub first_source: String,
+    /// The value of the field in the earlier layer.
+    pub first_setting: String,
+    /// The name of the later layer.
+    pub second_source: String,
+    /// The value of the field in the later layer.
+    pub second_setting: String,
+}
+
+impl core::fmt::Display for SettingConflict {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let SettingConflict {
+            field,
+            first_source,
+            first_setting,
+            second_source,
+            second_setting,
+        } = self;
+        write!(f, "The field {field} was set first to {first_setting} in {first_source} and then to {second_setting} in {second_source}")
+    }
+}
+
 /// All possible things that can go wrong when using `partial_config`.
 #[derive(Debug)]
 pub enum Error {
@@ -22,9 +164,22 @@ pub enum Error {
         // TODO: Consider using an array with fixed capacity and avoid allocation.
         required_fields: Vec<MissingField<'static>>,
     },
+    /// Some of the fields are invalid, and some may also be missing. This is only reported if at
+    /// least one field is invalid, otherwise missing fields are reported as
+    /// [`Error::MissingFields`].
+    FieldIssues { issues: Vec<FieldIssue> },
     /// A field that is supposed to be a number failed to be parsed from a string. Provided for
     /// convenience.
-    ParseIntError(std::num::ParseIntError),
+    ParseIntError(core::num::ParseIntError),
+    /// An integer field failed to parse. Unlike [`Error::ParseFieldError`], the error keeps its
+    /// concrete type, so that it can be matched on, e.g. for an overflow.
+    ParseIntFieldError {
+        field_name: &'static str,
+        field_type: &'static str,
+        /// The value that failed to parse, as it was specified.
+        value: String,
+        error: core::num::ParseIntError,
+    },
     /// A single setting was specified in two layers, and the two do not agree. This is useful in
     /// cases where you want to debug a _sticky_ setting that is strictly not supposed to be set,
     /// but is. It is also used for different aliases in the `EnvSourced` infrastructure of this
@@ -35,32 +190,54 @@ pub enum Error {
         second_source: String,
         second_setting: String,
     },
+    /// A layer given to [`crate::Strict`] specifies fields differently from the earlier layers.
+    /// Contains every conflicting field, so that they can be reported at once.
+    InconsistentSettings { conflicts: Vec<SettingConflict> },
     /// The field failed to parse. This is a more generic, and therefore less useful version of the
     /// other errors. Consider creating your own error type if you plan to do anything other than
     /// print the error message after this point.
+    #[cfg(feature = "std")]
     ParseFieldError {
         field_name: &'static str,
         field_type: &'static str,
+        /// The value that failed to parse, as it was specified.
+        value: String,
         error_condition: Box<dyn std::error::Error + Send + Sync>,
     },
-    #[cfg(feature = "serde")]
+    /// None of the alternatives of a [`crate::FirstOf`] source succeeded. Contains the name of
+    /// each alternative together with the reason it failed, in the order they were tried.
+    NoSourceSucceeded { failures: Vec<(String, Error)> },
+    /// A setting given to an override source, e.g. on the command line, is not of the form
+    /// `path.to.field=value`, or assigns to a field below one that is already set to a value.
+    InvalidOverride { setting: String },
+    /// A setting refers to the environment variable `var` as `${var}`, but it is not set.
+    #[cfg(feature = "interpolate")]
+    MissingInterpolation { var: String, field: String },
+    #[cfg(all(feature = "serde", feature = "std"))]
     /// The file failed to read.
     FileReadError(crate::serde_support::FileReadError),
     #[cfg(feature = "eyre")]
     /// This is a more sophisticated version of the `Box<dyn std::error::Error`, that can be
     /// cloned. Highly recommended but not required.
     EyreReport(eyre::Report),
+    #[cfg(feature = "config-compat")]
+    /// The configuration assembled with the `config` crate failed to load or deserialize.
+    ConfigCrate(config::ConfigError),
 }
 
-#[cfg(feature = "serde")]
+#[cfg(all(feature = "serde", feature = "std"))]
 impl From<crate::serde_support::FileReadError> for Error {
     fn from(value: crate::serde_support::FileReadError) -> Self {
-        Self::FileReadError(value)
+        match value {
+            #[cfg(feature = "interpolate")]
+            crate::serde_support::FileReadError::Interpolation(err) => *err,
+            value => Self::FileReadError(value),
+        }
     }
 }
 
-impl From<std::num::ParseIntError> for Error {
-    fn from(value: std::num::ParseIntError) -> Self {
+impl From<core::num::ParseIntError> for Error {
+    fn from(value: core::num::ParseIntError) -> Self {
         Self::ParseIntError(value)
     }
 }
@@ -72,14 +249,46 @@ impl From<eyre::Report> for Error {
     }
 }
 
+#[cfg(feature = "config-compat")]
+impl From<config::ConfigError> for Error {
+    fn from(value: config::ConfigError) -> Self {
+        Self::ConfigCrate(value)
+    }
+}
+
 impl core::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::MissingFields { required_fields } => {
-                let fields: Vec<&str> = required_fields.iter().map(|field| field.0).collect();
+                let fields: Vec<String> = required_fields
+                    .iter()
+                    .map(|field| {
+                        if field.type_name.is_empty() {
+                            field.path.to_string()
+                        } else {
+                            format!("{}: {}", field.path, field.type_name)
+                        }
+                    })
+                    .collect();
                 write!(f, "The required fields [{}] were not specified in any of the configuration sources", fields.join(", "))
             }
+            Error::FieldIssues { issues } => {
+                let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "The configuration has the following problems: {}",
+                    issues.join("; ")
+                )
+            }
             Error::ParseIntError(per) => write!(f, "Failed to parse integer. {per}"),
+            Error::ParseIntFieldError {
+                field_name,
+                field_type,
+                value,
+                error,
+            } => {
+                write!(f, "The field {field_name} failed to convert to {field_type} from value {value:?}, because of {error}")
+            }
             Error::InconsistentSetting {
                 first_source,
                 first_setting,
@@ -88,23 +297,62 @@ impl core::fmt::Display for Error {
             } => {
                 write!(f, "The field was set twice first to {first_setting} in {first_source} and then a second time to {second_setting} in {second_source}")
             }
+            Error::InconsistentSettings { conflicts } => {
+                let conflicts: Vec<String> = conflicts.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "The layers disagree on the following fields: {}",
+                    conflicts.join("; ")
+                )
+            }
+            #[cfg(feature = "std")]
             Error::ParseFieldError {
                 field_name,
                 field_type,
+                value,
                 error_condition,
             } => {
-                write!(f, "The field {field_name} failed to convert to {field_type}, because of {error_condition}")
+                write!(f, "The field {field_name} failed to convert to {field_type} from value {value:?}, because of {error_condition}")
+            }
+            Error::NoSourceSucceeded { failures } => {
+                let failures: Vec<String> = failures
+                    .iter()
+                    .map(|(source, err)| format!("`{source}`: {err}"))
+                    .collect();
+                write!(
+                    f,
+                    "None of the configuration sources succeeded: {}",
+                    failures.join("; ")
+                )
+            }
+            Error::InvalidOverride { setting } => {
+                write!(
+                    f,
+                    "The override {setting:?} is not of the form `path.to.field=value`"
+                )
+            }
+            #[cfg(feature = "interpolate")]
+            Error::MissingInterpolation { var, field } => {
+                write!(
+                    f,
+                    "The field {field} refers to the environment variable {var}, which is not set"
+                )
             }
             #[cfg(feature = "eyre")]
             Error::EyreReport(report) => {
                 write!(f, "{report:?}")
             }
-            #[cfg(feature = "serde")]
+            #[cfg(all(feature = "serde", feature = "std"))]
             Error::FileReadError(err) => {
                 write!(f, "File read error: `{}`", err)
             }
+            #[cfg(feature = "config-compat")]
+            Error::ConfigCrate(err) => {
+                write!(f, "`config` crate error: {err}")
+            }
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
@@ -0,0 +1,135 @@
+//! The rendering of the errors with which `serde` rejects configuration files, e.g. a typo in the
+//! name of a setting. The messages of `serde` are written for programmers, so the file sources
+//! pass them through a [`ConfigErrorFormat`], which applications can replace with
+//! [`set_error_format`], e.g. to colour them or to add suggestions of their own.
+
+use std::sync::OnceLock;
+
+/// Renders the errors of configuration files for the [`core::fmt::Display`] of
+/// [`crate::serde_support::FileReadError`]. Each method defaults to the rendering of
+/// [`DefaultErrorFormat`], so that only the formats of interest need to be overridden.
+pub trait ConfigErrorFormat: Send + Sync {
+    /// Render an error of a TOML document, given the `contents` of the file it was read from, if
+    /// they were kept.
+    #[cfg(feature = "toml")]
+    fn format_toml(&self, error: &toml::de::Error, contents: Option<&str>) -> String {
+        let mut message = match contents.and_then(|contents| toml_location(error, contents)) {
+            Some((line, column, text)) => {
+                let gutter = " ".repeat(line.to_string().len());
+                let indent = " ".repeat(column - 1);
+                format!(
+                    "Error parsing TOML file at line {line}, column {column}\n{line} | {text}\n{gutter} | {indent}^\n{}",
+                    error.message().trim_end()
+                )
+            }
+            None => format!("Error parsing TOML file {error}"),
+        };
+        if let Some(suggestion) = suggestion(error.message()) {
+            message.push_str(&format!("\nhelp: did you mean `{suggestion}`?"));
+        }
+        message
+    }
+
+    /// Render an error of a JSON document.
+    #[cfg(feature = "json")]
+    fn format_json(&self, error: &serde_json::Error) -> String {
+        let mut message = format!("Error parsing JSON file {error}");
+        if let Some(suggestion) = suggestion(&error.to_string()) {
+            message.push_str(&format!(", did you mean `{suggestion}`?"));
+        }
+        message
+    }
+}
+
+/// The format used unless another one is set. It keeps the line, column and excerpt of the
+/// document that the TOML errors carry, and for an unknown key or variant suggests the closest
+/// of the expected ones, e.g. "did you mean `port`?".
+pub struct DefaultErrorFormat;
+
+impl ConfigErrorFormat for DefaultErrorFormat {}
+
+static FORMAT: OnceLock<Box<dyn ConfigErrorFormat>> = OnceLock::new();
+
+/// Render all further errors of configuration files with `format` instead of
+/// [`DefaultErrorFormat`]. The format can only be set once, so that libraries cannot change the
+/// messages of the application.
+///
+/// # Errors
+///
+/// - If a format has already been set, in which case `format` is handed back.
+pub fn set_error_format(
+    format: Box<dyn ConfigErrorFormat>,
+) -> Result<(), Box<dyn ConfigErrorFormat>> {
+    FORMAT.set(format)
+}
+
+/// The format set with [`set_error_format`], or [`DefaultErrorFormat`].
+#[cfg(any(feature = "toml", feature = "json"))]
+pub(crate) fn error_format() -> &'static dyn ConfigErrorFormat {
+    FORMAT
+        .get()
+        .map_or(&DefaultErrorFormat, |format| format.as_ref())
+}
+
+/// The line and column at which `error` was found in `contents`, both counted from 1, and the
+/// text of that line, if the error knows its location.
+#[cfg(feature = "toml")]
+pub fn toml_location<'a>(
+    error: &toml::de::Error,
+    contents: &'a str,
+) -> Option<(usize, usize, &'a str)> {
+    let offset = error.span()?.start.min(contents.len());
+    let before = contents.get(..offset)?;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let line = contents[line_start..].lines().next().unwrap_or_default();
+    Some((
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+        line,
+    ))
+}
+
+/// The closest of the expected names in a message of `serde` such as ``unknown field `prot`,
+/// expected one of `host`, `port` ``, if it is close enough to be a likely typo.
+pub fn suggestion(message: &str) -> Option<&str> {
+    let rest = message
+        .split_once("unknown field `")
+        .or_else(|| message.split_once("unknown variant `"))?
+        .1;
+    let (unknown, expected) = rest.split_once('`')?;
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| (distance(unknown, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= (candidate.chars().count() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of single character insertions, deletions, substitutions and swaps of adjacent
+/// characters that turn `a` into `b`, so that e.g. `prot` is a single edit away from `port`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = table[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = substitution
+                .min(table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(table[i - 2][j - 2] + 1);
+            }
+            table[i][j] = best;
+        }
+    }
+    table[a.len()][b.len()]
+}
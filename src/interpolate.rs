@@ -0,0 +1,48 @@
+//! Expansion of `${VAR}` references in string settings, e.g. `data_dir = "${HOME}/myapp"`, from
+//! the environment. Applied by the `serde` file sources to the string fields of the partial after
+//! deserialization, through [`crate::Partial::interpolate`].
+
+use crate::Error;
+
+/// Replace every `${VAR}` in `value` with what `lookup` returns for `VAR`. Text that is not of
+/// this form, including a lone `$` or an unterminated `${`, is kept as is.
+///
+/// # Errors
+///
+/// - [`Error::MissingInterpolation`] naming the first variable for which `lookup` returns `None`,
+///   and `field`.
+pub fn expand(
+    value: &str,
+    field: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<String, Error> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let var = &rest[start + 2..start + 2 + len];
+        let replacement = lookup(var).ok_or_else(|| Error::MissingInterpolation {
+            var: var.to_owned(),
+            field: field.to_owned(),
+        })?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&replacement);
+        rest = &rest[start + 3 + len..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Qualify the field reported by the interpolation of the flattened structure in `field`, so that
+/// it reads as a dotted path, e.g. `storage.data_dir`.
+pub fn within(field: &str, result: Result<(), Error>) -> Result<(), Error> {
+    result.map_err(|err| match err {
+        Error::MissingInterpolation { var, field: inner } => Error::MissingInterpolation {
+            var,
+            field: format!("{field}.{inner}"),
+        },
+        other => other,
+    })
+}
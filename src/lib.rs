@@ -24,8 +24,10 @@
 //!     of configuraiton. For example, with the `serde` and `toml` features enabled there is an
 //!     implementation of `Source` for the `path`-like objects that automatically resolves to a
 //!     configuration layer for any structure for which `serde::Deserialize` can be automatically
-//!     derived. We plan to add a custom approach to this, because they way that `serde` handles error
-//!     reporting is not suitable for configuration files potentially written by humans.
+//!     derived. The way that `serde` handles error reporting is not suitable for configuration
+//!     files potentially written by humans, so the errors are rendered by a
+//!     [`error_format::ConfigErrorFormat`], which suggests the closest key for a typo and can be
+//!     replaced.
 //!
 //! # Examples
 //!
@@ -58,11 +60,50 @@
 //! problem and then report the next one. In order to do so, you as the programmer would have to
 //! write a little bit of tedious code, which you get for free by simply deriving [`HasPartial`] on
 //! your type.
+//!
+//! # `no_std`
+//!
+//! The core traits, and the code generated by [`HasPartial`], only need `alloc`. Disable the
+//! default `std` feature to use them in a `#![no_std]` crate, e.g. with compiled-in [`Source`]s.
+//! The [`env`] and [`serde_support`] modules, as well as the `std::error::Error` implementations,
+//! require `std`.
+//!
+//! # Logging
+//!
+//! Every layer is announced with a `Sourcing configuration from ...` message, which goes to
+//! `tracing` or `log` if the feature of the same name is enabled, and to the [`report`] module
+//! otherwise, which prints to the standard error unless a reporter is set with
+//! [`set_reporter`]. Libraries that do not want to print on behalf of the application can enable
+//! the `quiet` feature, which omits these messages without changing anything else.
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    format,
+    string::String,
+    vec,
+    vec::Vec,
+};
 use core::fmt::Debug;
 mod error;
 
-pub use error::{Error, MissingField};
+// The file format features only extend the `serde` support, so without it they would silently do
+// nothing, and the missing sources would be reported far away from the cause.
+#[cfg(all(feature = "toml", not(feature = "serde")))]
+compile_error!("The \"toml\" feature requires the \"serde\" feature. Please enable both.");
+#[cfg(all(feature = "json", not(feature = "serde")))]
+compile_error!("The \"json\" feature requires the \"serde\" feature. Please enable both.");
+#[cfg(all(feature = "tracing", feature = "log"))]
+compile_error!("The features \"tracing\" and \"log\" are mutually exclusive. Please either use pure tracing, or enable the \"log\" feature in \"tracing\" and use the \"log\" feature of this crate.");
+
+pub use error::{Error, FieldIssue, MissingField, SettingConflict};
+
+#[cfg(feature = "std")]
+pub use report::set_reporter;
 
 #[cfg(feature = "derive")]
 pub use partial_config_derive::HasPartial;
@@ -70,6 +111,68 @@ pub use partial_config_derive::HasPartial;
 #[cfg(feature = "derive")]
 pub use partial_config_derive::EnvSourced;
 
+/// Re-exports used by the code generated by the derive macros, so that it compiles in `no_std`
+/// crates that do not declare `extern crate alloc`. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{format, string::String, string::ToString, vec::Vec};
+
+    /// Checks that a structure with `#[env]` attributes also derives `EnvSourced`. The inherent
+    /// `check` is only found if it does, otherwise the deprecated one of [`NotEnvSourced`] is
+    /// called, which is the only way to warn from a derive macro on stable Rust.
+    #[cfg(feature = "std")]
+    pub struct EnvProbe<T>(pub core::marker::PhantomData<T>);
+
+    /// Implemented by the `EnvSourced` derive macro, even if the expansion fails, in which case
+    /// the errors are reported without the warning of [`NotEnvSourced`].
+    #[cfg(feature = "std")]
+    pub trait DerivesEnvSourced {}
+
+    #[cfg(feature = "std")]
+    impl<T: DerivesEnvSourced> EnvProbe<T> {
+        pub fn check(&self) {}
+    }
+
+    #[cfg(feature = "std")]
+    pub trait NotEnvSourced {
+        #[deprecated(
+            note = "The `#[env]` attributes have no effect, because the structure does not derive `EnvSourced`. Add `partial_config::EnvSourced` to the derives."
+        )]
+        fn check(&self) {}
+    }
+
+    #[cfg(feature = "std")]
+    impl<T> NotEnvSourced for &EnvProbe<T> {}
+
+    /// Called for a required field that is marked `#[partial_only(serde(skip))]` without a
+    /// `#[partial_default]`, to warn that file layers can never provide it.
+    #[deprecated(
+        note = "The field is required, but file layers skip it and it has no `#[partial_default]`, so `build` fails unless another source provides it. Add `#[partial_default]`, or `#[allow(deprecated)]` on the structure if a source does provide it."
+    )]
+    pub fn never_set() {}
+}
+
+/// Announce that a layer is obtained from the source of the given kind, see the [logging section
+/// of the crate documentation](crate#logging). Omitted if the `quiet` feature is enabled.
+#[allow(unused_variables)]
+pub(crate) fn log_source(kind: &str, profile: Option<&str>) {
+    let profile = profile
+        .map(|profile| format!(" (profile `{profile}`)"))
+        .unwrap_or_default();
+    #[cfg(all(feature = "tracing", not(feature = "quiet")))]
+    tracing::info!("Sourcing configuration from `{kind}`{profile}");
+    #[cfg(all(feature = "log", not(feature = "quiet")))]
+    log::info!("Sourcing configuration from `{kind}`{profile}");
+    #[cfg(all(
+        feature = "std",
+        not(any(feature = "tracing", feature = "log", feature = "quiet"))
+    ))]
+    crate::report::report(
+        crate::report::Level::Info,
+        &format!("Sourcing configuration from `{kind}`{profile}"),
+    );
+}
+
 /// Implementors of this trait are considered partial states of the full configuration structure
 /// which is [`Partial::Target`] in this case. If you are implementing this trait manually, pay
 /// close attention to the documentation of the provided methods. If your partial structure
@@ -93,6 +196,48 @@ pub trait Partial: Default {
     /// failing as soon as the first one is identified.
     fn build(self) -> Result<Self::Target, Self::Error>;
 
+    /// Same as [`Partial::build`], but keeps `self`, so that the same layers can be validated
+    /// repeatedly, e.g. in a REPL that edits the configuration.
+    fn build_ref(&self) -> Result<Self::Target, Self::Error>
+    where
+        Self: Clone,
+    {
+        self.clone().build()
+    }
+
+    /// Same as [`Partial::build`], but the fields that no layer specifies are taken from
+    /// `defaults`, e.g. a worker count computed from the number of CPUs at runtime, which
+    /// `#[partial_default]` cannot express. The [`HasPartial`] derive macro implements the
+    /// required conversion, which specifies every field of the target in a layer.
+    fn build_with_defaults(self, defaults: Self::Target) -> Result<Self::Target, Self::Error>
+    where
+        Self: From<Self::Target>,
+    {
+        Self::from(defaults).override_with(self).build()
+    }
+
+    /// Apply an RFC 7386 JSON Merge Patch to this layer, e.g. one received by an administrative
+    /// HTTP endpoint. The keys of `patch` set the fields of the same name, a `null` clears the
+    /// field so that it is no longer specified by this layer, and absent keys leave the fields
+    /// alone. A field of a flattened structure is patched by a nested object. The explicit `None`
+    /// of a `#[partial_nullable]` field is kept unless the patch mentions the field.
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::FileReadError`] if the patched layer does not deserialize, e.g. because a value
+    ///   has the wrong type. `self` is left unchanged in that case.
+    #[cfg(feature = "json")]
+    fn apply_merge_patch(&mut self, patch: serde_json::Value) -> Result<(), Error>
+    where
+        Self: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let json = |err| Error::from(serde_support::FileReadError::Json(err));
+        let mut document = serde_json::to_value(&*self).map_err(json)?;
+        serde_support::merge_patch(&mut document, patch);
+        *self = serde_json::from_value(document).map_err(json)?;
+        Ok(())
+    }
+
     /// Obtain [`Self`] from an object that is known to be a [`Source`] of the appropriate partial
     /// configuraiton. You should not override this function, unless you want to change the
     /// reporting.
@@ -109,20 +254,184 @@ pub trait Partial: Default {
     where
         <Self as Partial>::Error: From<<T as Source<<Self as Partial>::Target>>::Error>,
     {
-        #[cfg(feature = "tracing")]
-        tracing::info!("Sourcing configuration from `{}`", value.name());
-        #[cfg(feature = "log")]
-        log::info!("Sourcing configuration from `{}`", value.name());
-        #[cfg(not(any(feature = "tracing", feature = "log")))]
-        println!("Sourcing configuration from `{}`", value.name());
+        log_source(&value.source_kind(), None);
         let partial = value.to_partial()?;
         Ok(self.override_with(partial))
     }
 
+    /// Same as [`Partial::source`], but for sources that need to be awaited, e.g. a remote
+    /// secrets manager. Every synchronous [`Source`] is also an [`AsyncSource`], so the layers can
+    /// be mixed freely.
+    #[cfg(feature = "async")]
+    fn source_async<T: AsyncSource<Self::Target>>(
+        self,
+        value: T,
+    ) -> impl core::future::Future<Output = Result<Self, Self::Error>>
+    where
+        <Self as Partial>::Error: From<<T as AsyncSource<<Self as Partial>::Target>>::Error>,
+    {
+        async move {
+            log_source(&value.source_kind(), None);
+            let partial = value.to_partial().await?;
+            Ok(self.override_with(partial))
+        }
+    }
+
+    /// Same as [`Partial::source`], but borrows the source instead of consuming it. See
+    /// [`Source::to_partial_ref`].
+    fn source_ref<T: Source<Self::Target> + Clone>(self, value: &T) -> Result<Self, Self::Error>
+    where
+        <Self as Partial>::Error: From<<T as Source<<Self as Partial>::Target>>::Error>,
+    {
+        log_source(&value.source_kind(), None);
+        let partial = value.to_partial_ref()?;
+        Ok(self.override_with(partial))
+    }
+
+    /// Discard every layer applied so far, e.g. for a "Reset to defaults" button in a
+    /// configuration editor. The result is the same as that of [`Default::default`].
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
     /// If `other` contains values that are specified and different from `self`, or `self` is
     /// empty, replace the value with the other. Otherwise keep the one that is specified, so if
     /// `self` has a value specified, and `other` has `None`, keep the `Some` value.
     fn override_with(self, other: Self) -> Self;
+
+    /// Fold `layers` with [`Partial::override_with`], starting from [`Default::default`], so
+    /// that the later layers take precedence, e.g. for partials collected from plugins.
+    fn merge_all<I: IntoIterator<Item = Self>>(layers: I) -> Self {
+        layers
+            .into_iter()
+            .fold(Self::default(), |merged, layer| merged.override_with(layer))
+    }
+
+    /// Render the resolved state of this layer for diagnostics, e.g. for a `--check-config` mode
+    /// that prints the configuration and exits without starting the application. The default
+    /// implementation uses the [`Debug`] representation. The [`HasPartial`] derive macro
+    /// generates an implementation that prints one field per line, marking required fields that
+    /// are not specified as `<missing>` and optional fields that are not specified as
+    /// `<default>`, if `Debug` is among the `partial_derives`.
+    fn describe(&self) -> String
+    where
+        Self: Debug,
+    {
+        format!("{self:#?}")
+    }
+
+    /// Expand the `${VAR}` references in the string fields of this layer with `lookup`, see
+    /// [`interpolate::expand`]. Called by the file sources of [`serde_support`] after
+    /// deserialization, with the environment as `lookup`. The [`HasPartial`] derive macro
+    /// generates an implementation for fields of type `String` and `Option<String>`, and for
+    /// flattened structures; the default implementation leaves the layer alone.
+    #[cfg(feature = "interpolate")]
+    fn interpolate(&mut self, lookup: &dyn Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let _ = lookup;
+        Ok(())
+    }
+
+    /// Same as [`Partial::source`], but records every field that the layer changes in `log`, so
+    /// that the resolution of the configuration can be audited or replayed after
+    /// [`Partial::build`]. Changes are detected with [`Comparable::changes`].
+    #[cfg(feature = "audit")]
+    fn source_logged<T: Source<Self::Target>>(
+        self,
+        value: T,
+        log: &mut Vec<ResolutionStep>,
+    ) -> Result<Self, Self::Error>
+    where
+        Self: Comparable,
+        <Self as Partial>::Error: From<<T as Source<<Self as Partial>::Target>>::Error>,
+    {
+        let name = value.name();
+        log_source(&value.source_kind(), None);
+        let partial = value.to_partial()?;
+        log.extend(
+            self.changes(&partial)
+                .into_iter()
+                .map(|change| ResolutionStep {
+                    source: name.clone(),
+                    field: change.field,
+                    old: change.from,
+                    new: change.to.unwrap_or_default(),
+                }),
+        );
+        Ok(self.override_with(partial))
+    }
+}
+
+/// A partial whose layers can be compared field by field, rendering the values that differ with
+/// [`Debug`]. The [`HasPartial`] derive macro implements it if `Debug` is among the
+/// `partial_derives` and the structure has no type parameters.
+#[diagnostic::on_unimplemented(
+    message = "the layers of `{Self}` cannot be compared",
+    note = "add `Debug` to the `#[partial_derives]` of the structure"
+)]
+pub trait Comparable: Partial {
+    /// List the fields that are specified in both `self` and `other`, but with different values.
+    /// Used by [`Strict`] to detect layers that disagree.
+    fn conflicts(&self, other: &Self) -> Vec<FieldChange>;
+
+    /// List the fields that applying `other` as a layer with [`Partial::override_with`] would
+    /// change, i.e. the fields that `other` specifies with a different value. Used by
+    /// [`Partial::source_logged`].
+    fn changes(&self, other: &Self) -> Vec<FieldChange>;
+}
+
+/// A single difference between two partial layers, as reported by the `diff` method generated by
+/// the [`HasPartial`] derive macro. The values are rendered with [`Debug`], and are `None` if the
+/// field is not specified in the corresponding layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// The name of the field that differs.
+    pub field: &'static str,
+    /// The value in the original layer.
+    pub from: Option<String>,
+    /// The value in the other layer.
+    pub to: Option<String>,
+}
+
+/// The value of a field marked `#[partial_nullable]` in a layer that clears the value specified by
+/// the lower layers, rather than leaving it alone. The partial holds such a field as an
+/// `Option<Option<T>>`, where `None` leaves the field alone and `Some(None)` clears it, which is
+/// what `Unset` converts into:
+///
+/// ```rust
+/// # use partial_config::{HasPartial, Partial, Unset};
+/// #[derive(HasPartial)]
+/// pub struct Output {
+///     #[partial_nullable]
+///     pub color: Option<bool>,
+/// }
+///
+/// // E.g. the file sets `color = true`, but `--no-color` was passed on the command line.
+/// let file = PartialOutput { color: Some(Some(true)) };
+/// let cli = PartialOutput { color: Unset.into() };
+/// assert_eq!(file.override_with(cli).build().unwrap().color, None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Unset;
+
+impl<T> From<Unset> for Option<Option<T>> {
+    fn from(_: Unset) -> Self {
+        Some(None)
+    }
+}
+
+/// A single field being set by a layer, as recorded by [`Partial::source_logged`]. The values are
+/// rendered with [`Debug`].
+#[cfg(feature = "audit")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionStep {
+    /// The [`Source::name`] of the layer.
+    pub source: String,
+    /// The name of the field that was set.
+    pub field: &'static str,
+    /// The value before the layer was applied, if any.
+    pub old: Option<String>,
+    /// The value after the layer was applied.
+    pub new: String,
 }
 
 /// Marker trait that is used to allow a `derive` macro to generate a new structure. This trait is
@@ -147,10 +456,71 @@ pub trait Source<C: HasPartial> {
     /// [`Partial::source`] for type checking.
     fn to_partial(self) -> Result<C::Partial, Self::Error>;
 
+    /// Obtain a partial layer without consuming `self`, so that the same source can be layered
+    /// into several partials. Used by [`Partial::source_ref`]. The default implementation clones
+    /// `self`; override it if cloning is expensive and the layer can be obtained from a reference.
+    fn to_partial_ref(&self) -> Result<C::Partial, Self::Error>
+    where
+        Self: Clone,
+    {
+        self.clone().to_partial()
+    }
+
     /// The name that is being printed whenever this layer of configuration is being parsed. If you
     /// came across this method to silence the `Sourcing configuration from XXX` message, instead
-    /// simply override the [`Partial::source`] method instead.
+    /// enable the `quiet` feature of this crate.
+    fn name(&self) -> String;
+
+    /// The kind of this source, e.g. `Environment Variables`, for the `Sourcing configuration
+    /// from ...` messages and for telling layers apart programmatically. Sources with a fixed
+    /// name return it without allocating; the default implementation returns [`Source::name`].
+    fn source_kind(&self) -> Cow<'static, str> {
+        Cow::Owned(self.name())
+    }
+}
+
+/// The asynchronous counterpart of [`Source`], for configuration that cannot be obtained without
+/// blocking, e.g. from a secrets manager over the network. Used by [`Partial::source_async`].
+#[cfg(feature = "async")]
+pub trait AsyncSource<C: HasPartial> {
+    type Error: Debug;
+
+    /// Obtain a partial layer from `Self`. Not user facing, but used inside the
+    /// [`Partial::source_async`] for type checking.
+    fn to_partial(
+        self,
+    ) -> impl core::future::Future<Output = Result<C::Partial, Self::Error>> + Send;
+
+    /// The name that is being printed whenever this layer of configuration is being parsed.
     fn name(&self) -> String;
+
+    /// See [`Source::source_kind`].
+    fn source_kind(&self) -> Cow<'static, str> {
+        Cow::Owned(self.name())
+    }
+}
+
+#[cfg(feature = "async")]
+impl<C, S> AsyncSource<C> for S
+where
+    C: HasPartial,
+    C::Partial: Send,
+    S: Source<C> + Send,
+    S::Error: Send,
+{
+    type Error = S::Error;
+
+    async fn to_partial(self) -> Result<C::Partial, Self::Error> {
+        Source::to_partial(self)
+    }
+
+    fn name(&self) -> String {
+        Source::name(self)
+    }
+
+    fn source_kind(&self) -> Cow<'static, str> {
+        Source::source_kind(self)
+    }
 }
 
 impl<T, C, E> Source<C> for Option<T>
@@ -170,6 +540,238 @@ where
     }
 }
 
+/// A source with a custom [`Source::name`], e.g. to tell apart two files of the same format in
+/// the `Sourcing configuration from ...` log messages. The layer is obtained from the wrapped
+/// source.
+#[derive(Debug, Clone)]
+pub struct NamedSource<S>(pub String, pub S);
+
+impl<C, S> Source<C> for NamedSource<S>
+where
+    C: HasPartial,
+    S: Source<C>,
+{
+    type Error = S::Error;
+
+    fn to_partial(self) -> Result<C::Partial, Self::Error> {
+        self.1.to_partial()
+    }
+
+    fn name(&self) -> String {
+        self.0.clone()
+    }
+}
+
+/// An object-safe version of [`Source`], so that sources of different types can be stored
+/// together, as in [`FirstOf`]. Implemented for every [`Source`] whose error converts into
+/// [`Error`].
+pub trait DynSource<C: HasPartial> {
+    /// Obtain a partial layer from the boxed source.
+    fn to_partial_boxed(self: Box<Self>) -> Result<C::Partial, Error>;
+
+    /// See [`Source::name`].
+    fn name(&self) -> String;
+
+    /// See [`Source::source_kind`].
+    fn source_kind(&self) -> Cow<'static, str> {
+        Cow::Owned(self.name())
+    }
+}
+
+impl<C, S> DynSource<C> for S
+where
+    C: HasPartial,
+    S: Source<C>,
+    S::Error: Into<Error>,
+{
+    fn to_partial_boxed(self: Box<Self>) -> Result<C::Partial, Error> {
+        Source::to_partial(*self).map_err(Into::into)
+    }
+
+    fn name(&self) -> String {
+        Source::name(self)
+    }
+
+    fn source_kind(&self) -> Cow<'static, str> {
+        Source::source_kind(self)
+    }
+}
+
+/// A source that tries each of the alternatives in order and uses the first one that succeeds,
+/// e.g. the first of several candidate configuration file locations that exists and parses. The
+/// remaining alternatives are not consulted, so unlike applying each with [`Partial::source`],
+/// exactly one layer is used.
+///
+/// # Errors
+///
+/// If every alternative fails, [`Error::NoSourceSucceeded`] lists all of the failures.
+pub struct FirstOf<'a, C: HasPartial>(pub Vec<Box<dyn DynSource<C> + 'a>>);
+
+impl<C: HasPartial> Source<C> for FirstOf<'_, C> {
+    type Error = Error;
+
+    fn to_partial(self) -> Result<C::Partial, Self::Error> {
+        let mut failures = Vec::with_capacity(self.0.len());
+        for source in self.0 {
+            let name = source.name();
+            let kind = source.source_kind();
+            match source.to_partial_boxed() {
+                Ok(partial) => {
+                    log_source(&kind, None);
+                    return Ok(partial);
+                }
+                Err(err) => failures.push((name, err)),
+            }
+        }
+        Err(Error::NoSourceSucceeded { failures })
+    }
+
+    fn name(&self) -> String {
+        let names: Vec<String> = self
+            .0
+            .iter()
+            .map(|source| format!("`{}`", source.name()))
+            .collect();
+        format!("First of {}", names.join(", "))
+    }
+}
+
+/// A set of layers, some of which only apply to a particular profile, e.g. `production`. The
+/// base layers are always applied, followed by the layers registered for the profile selected
+/// with [`ProfiledStack::resolve`], each in the order in which they were registered. The layers
+/// of other profiles are not consulted.
+pub struct ProfiledStack<'a, C: HasPartial> {
+    base: Vec<Box<dyn DynSource<C> + 'a>>,
+    profiles: Vec<(String, Box<dyn DynSource<C> + 'a>)>,
+}
+
+impl<'a, C: HasPartial> ProfiledStack<'a, C> {
+    /// A stack without any layers.
+    pub fn new() -> Self {
+        Self {
+            base: Vec::new(),
+            profiles: Vec::new(),
+        }
+    }
+
+    /// Add a layer that applies regardless of the profile.
+    pub fn base(mut self, source: impl DynSource<C> + 'a) -> Self {
+        self.base.push(Box::new(source));
+        self
+    }
+
+    /// Add a layer that only applies if `profile` is selected.
+    pub fn profile(mut self, profile: impl Into<String>, source: impl DynSource<C> + 'a) -> Self {
+        self.profiles.push((profile.into(), Box::new(source)));
+        self
+    }
+
+    /// Combine the base layers with the layers of the `active` profile.
+    ///
+    /// # Errors
+    ///
+    /// - If obtaining any of the applicable layers fails.
+    pub fn resolve(self, active: &str) -> Result<C::Partial, Error> {
+        let profile = self
+            .profiles
+            .into_iter()
+            .filter(|(profile, _)| profile == active)
+            .map(|(_, source)| source);
+        self.base
+            .into_iter()
+            .chain(profile)
+            .try_fold(C::Partial::default(), |partial, source| {
+                log_source(&source.source_kind(), Some(active));
+                Ok(partial.override_with(source.to_partial_boxed()?))
+            })
+    }
+}
+
+impl<C: HasPartial> Default for ProfiledStack<'_, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A stricter way of combining layers than [`Partial::source`]: instead of silently overriding a
+/// field that an earlier layer has already specified, a layer that specifies a different value
+/// is rejected with [`Error::InconsistentSettings`], naming both layers. Useful when the layers
+/// are expected to complement rather than override each other, e.g. a set of `conf.d` files.
+///
+/// Conflicts are detected with [`Comparable::conflicts`].
+pub struct Strict<P: Comparable> {
+    layers: Vec<(String, P)>,
+}
+
+impl<P: Comparable> Strict<P> {
+    /// Start from `initial`, e.g. [`Default::default`].
+    pub fn new(initial: P) -> Self {
+        Self {
+            layers: vec![("Initial layer".to_owned(), initial)],
+        }
+    }
+
+    /// Add the layer obtained from `value`, unless it conflicts with any of the previous layers.
+    ///
+    /// # Errors
+    ///
+    /// - If obtaining the layer fails.
+    /// - If the layer specifies any fields differently from the previous layers, all of which
+    ///   are reported.
+    pub fn source<T: Source<P::Target>>(mut self, value: T) -> Result<Self, P::Error>
+    where
+        P::Error: From<T::Error> + From<Error>,
+    {
+        let name = value.name();
+        log_source(&value.source_kind(), None);
+        let partial = value.to_partial()?;
+        let conflicts: Vec<SettingConflict> = self
+            .layers
+            .iter()
+            .flat_map(|(previous, layer)| {
+                layer
+                    .conflicts(&partial)
+                    .into_iter()
+                    .map(|conflict| SettingConflict {
+                        field: conflict.field,
+                        first_source: previous.clone(),
+                        first_setting: conflict.from.unwrap_or_default(),
+                        second_source: name.clone(),
+                        second_setting: conflict.to.unwrap_or_default(),
+                    })
+            })
+            .collect();
+        if !conflicts.is_empty() {
+            return Err(Error::InconsistentSettings { conflicts }.into());
+        }
+        self.layers.push((name, partial));
+        Ok(self)
+    }
+
+    /// Discard every layer, including the initial one, so that the next layer is only checked
+    /// against [`Default::default`]. See [`Partial::reset`].
+    pub fn reset(&mut self) {
+        *self = Self::new(P::default());
+    }
+
+    /// Combine the layers into a single partial.
+    pub fn into_inner(self) -> P {
+        self.layers
+            .into_iter()
+            .fold(P::default(), |acc, (_, layer)| acc.override_with(layer))
+    }
+
+    /// Combine the layers and build the configuration. See [`Partial::build`].
+    ///
+    /// # Errors
+    ///
+    /// - Same as [`Partial::build`].
+    pub fn build(self) -> Result<P::Target, P::Error> {
+        self.into_inner().build()
+    }
+}
+
+#[cfg(feature = "std")]
 pub mod env {
     /// This is a marker trait that signals that this particular
     /// partial configuration has an environment variables source that
@@ -180,13 +782,93 @@ pub mod env {
         type Source: 'a + super::Source<Self> + Default;
     }
 
+    /// An environment variable source that can look up its variables under a common prefix. This
+    /// is generated together with [`EnvSourced`], and used for fields marked
+    /// `#[partial_flatten]`, so that e.g. the `cert` field of a `tls` sub-structure is read from
+    /// `TLS_CERT` rather than `CERT`.
+    pub trait PrefixedSource<C: super::HasPartial>: super::Source<C> {
+        /// Obtain a partial layer, prepending `prefix` to every candidate environment variable,
+        /// and reading the variables with `lookup` (see [`extract_from`]).
+        fn to_partial_prefixed(
+            self,
+            prefix: &str,
+            lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        ) -> Result<C::Partial, Self::Error>;
+
+        /// Obtain a partial layer, reading the variables with `lookup` instead of from the
+        /// process environment, e.g. from a `HashMap` in tests.
+        fn to_partial_from(
+            self,
+            lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        ) -> Result<C::Partial, Self::Error>
+        where
+            Self: Sized,
+        {
+            self.to_partial_prefixed("", lookup)
+        }
+    }
+
+    /// Read the environment variable `key` from the environment of the current process. This is
+    /// the lookup used by [`extract`] and by the derived [`EnvSourced`] sources.
+    ///
+    /// # Errors
+    ///
+    /// - Same as [`std::env::var`].
+    pub fn process_env(key: &str) -> Result<String, std::env::VarError> {
+        std::env::var(key)
+    }
+
+    /// A copy of the environment of the current process, taken once, so that every field of a
+    /// layer is read from the same environment even if another thread modifies it in between. The
+    /// derived [`EnvSourced`] sources read from a snapshot taken when the layer is obtained. A
+    /// snapshot can also be collected from pairs of strings, e.g. in tests.
+    #[derive(Debug, Clone, Default)]
+    pub struct EnvSnapshot(std::collections::HashMap<std::ffi::OsString, std::ffi::OsString>);
+
+    impl EnvSnapshot {
+        /// Copy all environment variables of the current process.
+        pub fn capture() -> Self {
+            Self(std::env::vars_os().collect())
+        }
+
+        /// Read the variable `key` from the snapshot. Use `&|key| snapshot.var(key)` as the
+        /// `lookup` of [`extract_from`] and [`PrefixedSource::to_partial_from`].
+        ///
+        /// # Errors
+        ///
+        /// - Same as [`std::env::var`] at the time the snapshot was taken.
+        pub fn var(&self, key: &str) -> Result<String, std::env::VarError> {
+            match self.0.get(std::ffi::OsStr::new(key)) {
+                Some(value) => value
+                    .clone()
+                    .into_string()
+                    .map_err(std::env::VarError::NotUnicode),
+                None => Err(std::env::VarError::NotPresent),
+            }
+        }
+    }
+
+    impl<K, V> FromIterator<(K, V)> for EnvSnapshot
+    where
+        K: Into<std::ffi::OsString>,
+        V: Into<std::ffi::OsString>,
+    {
+        fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+            Self(
+                iter.into_iter()
+                    .map(|(key, value)| (key.into(), value.into()))
+                    .collect(),
+            )
+        }
+    }
+
     /// Extract a string that corresponds to a consistent
     /// specification from an environment variable
     ///
     /// # Errors
     ///
     /// - If any specified candidate environment variables has two
-    ///  different specifications
+    ///   different specifications
     ///
     /// # Warns
     ///
@@ -194,7 +876,7 @@ pub mod env {
     /// in an `Err` variant being constructed.
     ///
     /// - `None` is returned if neither of the candidate environment
-    ///    variables was present, or all contained non-unicode values.
+    ///   variables was present, or all contained non-unicode values.
     ///
     /// - If two candidates are set to the same value, a warning is
     ///   printed.
@@ -202,37 +884,192 @@ pub mod env {
     /// - If either one of the candidates is set to a non-unicode
     ///   value, a warning is printed.
     pub fn extract(candidates: &[&str]) -> Result<Option<String>, super::Error> {
-        let mut found = None;
+        extract_from(candidates, &process_env)
+    }
+
+    /// Same as [`extract`], but the variables are read with `lookup`
+    /// instead of from the process environment. This allows tests to
+    /// provide the variables without modifying the global environment.
+    ///
+    /// # Errors
+    ///
+    /// - If any specified candidate environment variables has two
+    ///   different specifications
+    pub fn extract_from(
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<String>, super::Error> {
+        extract_with_source_from(candidates, lookup).map(|found| found.map(|(_, value)| value))
+    }
+
+    /// Same as [`extract`], but also returns the name of the candidate
+    /// environment variable that supplied the value, e.g. to tell
+    /// whether a deprecated alias was used.
+    ///
+    /// # Errors
+    ///
+    /// - If any specified candidate environment variables has two
+    ///   different specifications
+    pub fn extract_with_source(
+        candidates: &[&str],
+    ) -> Result<Option<(String, String)>, super::Error> {
+        extract_with_source_from(candidates, &process_env)
+    }
+
+    /// Same as [`extract_with_source`], but the variables are read with
+    /// `lookup`. See [`extract_from`].
+    ///
+    /// # Errors
+    ///
+    /// - If any specified candidate environment variables has two
+    ///   different specifications
+    pub fn extract_with_source_from(
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<(String, String)>, super::Error> {
+        first_consistent(
+            candidates,
+            |candidate| read_unicode(candidate, lookup),
+            String::clone,
+        )
+    }
+
+    /// The value of the environment variable `candidate`, skipping values that are not Unicode
+    /// with a warning.
+    fn read_unicode(
+        candidate: &str,
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Option<String> {
+        match lookup(candidate) {
+            Ok(value) => Some(value),
+            Err(std::env::VarError::NotPresent) => None,
+            Err(std::env::VarError::NotUnicode(thing)) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}");
+                #[cfg(feature = "log")]
+                log::warn!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}");
+                #[cfg(not(any(feature = "log", feature = "tracing")))]
+                crate::report::report(crate::report::Level::Warn, &format!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}"));
+                None
+            }
+        }
+    }
+
+    /// The value of the environment variable `candidate`, which need not be Unicode.
+    fn read_os(
+        candidate: &str,
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Option<std::ffi::OsString> {
+        match lookup(candidate) {
+            Ok(value) => Some(value.into()),
+            Err(std::env::VarError::NotUnicode(value)) => Some(value),
+            Err(std::env::VarError::NotPresent) => None,
+        }
+    }
+
+    /// Same as [`extract_prefixed_with_source`], but the `candidates` are tried in order and the
+    /// first one that is set wins, without checking the others. Used for fields marked
+    /// `#[env(ordered(SERVICE_PORT, SHARED_PORT))]`, where a specific variable overrides a
+    /// shared fallback.
+    ///
+    /// # Errors
+    ///
+    /// - Never, but the signature matches that of [`extract_prefixed_with_source`].
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn extract_ordered_prefixed_with_source(
+        prefix: &str,
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<(String, String)>, super::Error> {
+        Ok(first_present(prefix, candidates, |candidate| {
+            read_unicode(candidate, lookup)
+        }))
+    }
+
+    /// Same as [`extract_ordered_prefixed_with_source`] but for values that need not be Unicode,
+    /// e.g. paths.
+    ///
+    /// # Errors
+    ///
+    /// - Never, but the signature matches that of [`extract_os_prefixed_with_source`].
+    #[allow(clippy::unnecessary_wraps)]
+    pub fn extract_os_ordered_prefixed_with_source(
+        prefix: &str,
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<(String, std::ffi::OsString)>, super::Error> {
+        Ok(first_present(prefix, candidates, |candidate| {
+            read_os(candidate, lookup)
+        }))
+    }
+
+    /// The first of the `candidates`, with `prefix` prepended, for which `read` produces a value.
+    fn first_present<V>(
+        prefix: &str,
+        candidates: &[&str],
+        read: impl Fn(&str) -> Option<V>,
+    ) -> Option<(String, V)> {
+        with_prefix(prefix, candidates)
+            .into_iter()
+            .find_map(|candidate| read(&candidate).map(|value| (candidate, value)))
+    }
+
+    /// Same as [`extract_with_source_from`] but for values that need not be Unicode, e.g. paths,
+    /// with `prefix` prepended to each of the `candidates`. Non-Unicode values are returned as
+    /// they are, rather than skipped.
+    ///
+    /// # Errors
+    ///
+    /// - If any specified candidate environment variables has two
+    ///   different specifications
+    pub fn extract_os_prefixed_with_source(
+        prefix: &str,
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<(String, std::ffi::OsString)>, super::Error> {
+        let prefixed = with_prefix(prefix, candidates);
+        let prefixed: Vec<&str> = prefixed.iter().map(String::as_str).collect();
+        first_consistent(
+            &prefixed,
+            |candidate| read_os(candidate, lookup),
+            |value: &std::ffi::OsString| value.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// The first of the `candidates` for which `read` produces a value, provided that all other
+    /// candidates that produce a value agree with it. `render` is used for reporting.
+    fn first_consistent<V: PartialEq>(
+        candidates: &[&str],
+        read: impl Fn(&str) -> Option<V>,
+        render: impl Fn(&V) -> String,
+    ) -> Result<Option<(String, V)>, super::Error> {
+        let mut found: Option<(&str, V)> = None;
         for candidate in candidates {
-            match (&found, std::env::var(candidate)) {
-                (_, Err(std::env::VarError::NotPresent)) => continue,
-                (_, Err(std::env::VarError::NotUnicode(thing))) => {
+            let Some(value) = read(candidate) else {
+                continue;
+            };
+            match &found {
+                None => found = Some((candidate, value)),
+                Some((previous_key, previous)) if *previous == value => {
                     #[cfg(feature = "tracing")]
-                    tracing::warn!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}");
+                    tracing::warn!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}");
                     #[cfg(feature = "log")]
-                    log::warn!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}");
+                    log::warn!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}");
                     #[cfg(not(any(feature = "log", feature = "tracing")))]
-                    eprintln!("The value of the environment variable for `{candidate}` was not Unicode. Got {thing:?}");
+                    crate::report::report(crate::report::Level::Warn, &format!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}"));
                 }
-                (None, Ok(value)) => found = Some((candidate, value)),
-                (Some((previous_key, previous_string)), Ok(value)) if *previous_string == value => {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}");
-                    #[cfg(feature = "log")]
-                    log::warn!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}");
-                    #[cfg(not(any(feature = "log", feature = "tracing")))]
-                    eprintln!("Redundant specification of the environment variable {candidate}, which was previously set via {previous_key}");
-                }
-                (Some((previous_key, previous_string)), Ok(value)) => {
+                Some((previous_key, previous)) => {
+                    let previous_string = render(previous);
+                    let value = render(&value);
                     #[cfg(feature = "tracing")]
                     tracing::error!("Inconsistent specification via environment variable {candidate}. Expected {previous_string} found {value}");
                     #[cfg(feature = "log")]
                     log::error!("Inconsistent specification via environment variable {candidate}. Expected {previous_string} found {value}");
                     #[cfg(not(any(feature = "log", feature = "tracing")))]
-                    eprintln!("Inconsistent specification via environment variable {candidate}. Expected {previous_string} found {value}");
+                    crate::report::report(crate::report::Level::Error, &format!("Inconsistent specification via environment variable {candidate}. Expected {previous_string} found {value}"));
                     let err = super::Error::InconsistentSetting {
                         first_source: format!("Environment variable {previous_key}"),
-                        first_setting: previous_string.clone(),
+                        first_setting: previous_string,
                         second_source: format!("Environment variable {candidate}"),
                         second_setting: value,
                     };
@@ -240,13 +1077,217 @@ pub mod env {
                 }
             }
         }
-        Ok(found.map(|(_, value)| value))
+        Ok(found.map(|(candidate, value)| (candidate.to_string(), value)))
+    }
+
+    fn with_prefix(prefix: &str, candidates: &[&str]) -> Vec<String> {
+        candidates
+            .iter()
+            .map(|candidate| format!("{prefix}{candidate}"))
+            .collect()
+    }
+
+    /// Same as [`extract_with_source_from`], but with `prefix` prepended to each of the
+    /// `candidates`.
+    ///
+    /// # Errors
+    ///
+    /// - If any specified candidate environment variables has two
+    ///   different specifications
+    pub fn extract_prefixed_with_source(
+        prefix: &str,
+        candidates: &[&str],
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<Option<(String, String)>, super::Error> {
+        if prefix.is_empty() {
+            return extract_with_source_from(candidates, lookup);
+        }
+        let prefixed = with_prefix(prefix, candidates);
+        let prefixed: Vec<&str> = prefixed.iter().map(String::as_str).collect();
+        extract_with_source_from(&prefixed, lookup)
+    }
+
+    /// Read the environment variable `key` with `lookup`, treating an empty value as if the
+    /// variable were not set. Used for fields marked `#[env_empty_is_unset]`, so that e.g.
+    /// `PORT=""` does not fail to parse.
+    ///
+    /// # Errors
+    ///
+    /// - Same as `lookup`, and [`std::env::VarError::NotPresent`] if the value is empty.
+    pub fn non_empty(
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        key: &str,
+    ) -> Result<String, std::env::VarError> {
+        match lookup(key) {
+            Ok(value) if value.is_empty() => Err(std::env::VarError::NotPresent),
+            Err(std::env::VarError::NotUnicode(value)) if value.is_empty() => {
+                Err(std::env::VarError::NotPresent)
+            }
+            found => found,
+        }
+    }
+
+    /// Read the environment variable `key` with `lookup`, replacing any value, including one that
+    /// is not valid Unicode, by an empty string. Used for fields marked `#[env(DEBUG, flag)]`,
+    /// for which only whether the variable is set matters, so that e.g. `DEBUG=1` and
+    /// `VERBOSE=yes` are consistent.
+    ///
+    /// # Errors
+    ///
+    /// - [`std::env::VarError::NotPresent`] if the variable is not set.
+    pub fn presence(
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        key: &str,
+    ) -> Result<String, std::env::VarError> {
+        match lookup(key) {
+            Ok(_) | Err(std::env::VarError::NotUnicode(_)) => Ok(String::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Read the environment variable `key` with `lookup`, or if it is not set, the file named by
+    /// `{key}_FILE`, with surrounding whitespace trimmed. Used for fields marked
+    /// `#[env_file_fallback]`, following the convention for Docker secrets, e.g.
+    /// `DB_PASSWORD_FILE=/run/secrets/db_password`.
+    ///
+    /// # Errors
+    ///
+    /// - Same as `lookup`, and [`std::env::VarError::NotPresent`] if neither variable is set, or
+    ///   if the file cannot be read, in which case a warning is logged.
+    pub fn file_fallback(
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        key: &str,
+    ) -> Result<String, std::env::VarError> {
+        match lookup(key) {
+            Err(std::env::VarError::NotPresent) => {
+                let path = lookup(&format!("{key}_FILE"))?;
+                match std::fs::read_to_string(&path) {
+                    Ok(contents) => Ok(contents.trim().to_owned()),
+                    Err(e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Failed to read {key} from the file {path:?}: {e}");
+                        #[cfg(feature = "log")]
+                        log::warn!("Failed to read {key} from the file {path:?}: {e}");
+                        #[cfg(not(any(feature = "tracing", feature = "log")))]
+                        crate::report::report(
+                            crate::report::Level::Warn,
+                            &format!("Failed to read {key} from the file {path:?}: {e}"),
+                        );
+                        Err(std::env::VarError::NotPresent)
+                    }
+                }
+            }
+            found => found,
+        }
+    }
+
+    /// Parse `value` as the inner type of a single-field tuple struct, and `wrap` it, usually with
+    /// the constructor of the tuple struct. Used for fields marked `#[env_from_inner]`, so that
+    /// e.g. `struct Port(u16)` need not implement `FromStr`.
+    ///
+    /// # Errors
+    ///
+    /// - If the inner type fails to parse.
+    pub fn from_inner<I, T>(wrap: impl FnOnce(I) -> T, value: &str) -> Result<T, I::Err>
+    where
+        I: core::str::FromStr,
+    {
+        value.parse().map(wrap)
+    }
+
+    /// Parse each element of the list `value`, separated by `separator`, e.g. `a,b,c`. Used for
+    /// fields of type `Vec<T>` or `Option<Vec<T>>`. The elements are trimmed, and empty elements
+    /// are skipped, so that an empty variable is an empty list.
+    ///
+    /// # Errors
+    ///
+    /// - If any of the elements fails to parse.
+    pub fn split_list<T>(value: &str, separator: &str) -> Result<Vec<T>, T::Err>
+    where
+        T: core::str::FromStr,
+    {
+        value
+            .split(separator)
+            .map(str::trim)
+            .filter(|element| !element.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// The prefix selected by the profile named in the `profile_var` environment variable,
+    /// appended to `prefix`, e.g. `PROD_` for `CONFIG_PROFILE=prod`. If `profile_var` is not set,
+    /// `prefix` is returned unchanged. Used by the `#[env_profile_prefix(CONFIG_PROFILE)]`
+    /// attribute of the [`EnvSourced`] derive macro.
+    ///
+    /// # Errors
+    ///
+    /// - Same as [`extract_from`].
+    pub fn profile_prefix(
+        prefix: &str,
+        profile_var: &str,
+        lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+    ) -> Result<String, super::Error> {
+        Ok(match extract_from(&[profile_var], lookup)? {
+            Some(profile) => format!("{prefix}{}_", profile.to_uppercase()),
+            None => prefix.to_owned(),
+        })
+    }
+
+    /// An environment variable source for the profile chosen at runtime, e.g. from a command line
+    /// flag. Every candidate environment variable of `source` is prefixed with the upper-case
+    /// profile name, so that `Profile::new("dev", ServerEnv::new())` reads `DEV_SERVER_PORT`
+    /// instead of `SERVER_PORT`.
+    pub struct Profile<S> {
+        profile: String,
+        source: S,
+    }
+
+    impl<S> Profile<S> {
+        /// Read the variables of `source` for `profile`.
+        pub fn new(profile: impl Into<String>, source: S) -> Self {
+            Self {
+                profile: profile.into(),
+                source,
+            }
+        }
+    }
+
+    impl<C, S> super::Source<C> for Profile<S>
+    where
+        C: super::HasPartial,
+        S: PrefixedSource<C>,
+    {
+        type Error = S::Error;
+
+        fn to_partial(self) -> Result<C::Partial, Self::Error> {
+            let snapshot = EnvSnapshot::capture();
+            self.to_partial_prefixed("", &|key| snapshot.var(key))
+        }
+
+        fn name(&self) -> String {
+            format!("{} (profile `{}`)", self.source.name(), self.profile)
+        }
+    }
+
+    impl<C, S> PrefixedSource<C> for Profile<S>
+    where
+        C: super::HasPartial,
+        S: PrefixedSource<C>,
+    {
+        fn to_partial_prefixed(
+            self,
+            prefix: &str,
+            lookup: &dyn Fn(&str) -> Result<String, std::env::VarError>,
+        ) -> Result<C::Partial, Self::Error> {
+            let prefix = format!("{prefix}{}_", self.profile.to_uppercase());
+            self.source.to_partial_prefixed(&prefix, lookup)
+        }
     }
 }
 
-#[cfg(feature = "serde")]
+#[cfg(all(feature = "serde", feature = "std"))]
 pub mod serde_support {
-    use super::{HasPartial, Source};
+    use super::{HasPartial, Partial, Source};
 
     #[cfg(feature = "toml")]
     use std::io::Read;
@@ -261,7 +1302,17 @@ pub mod serde_support {
         Open(std::io::Error), // TODO: Implement proper `source` and other standard error traits.
 
         #[cfg(feature = "toml")]
-        Toml(toml::de::Error), // TODO: Implement proper `soruce` and standard error trait methods.
+        Toml(toml::de::Error), // TODO: Implement proper `source` and standard error trait methods.
+
+        /// A TOML file failed to parse or to deserialize. Unlike [`Self::Toml`], the contents of
+        /// the file are kept, so that a handler can show the offending line, like `rustc` does.
+        #[cfg(feature = "toml")]
+        TomlFile {
+            /// The error reported by `toml`.
+            error: Box<toml::de::Error>,
+            /// The contents of the file.
+            contents: String,
+        },
 
         #[cfg(feature = "json")]
         Json(serde_json::Error), // TODO: Implement proper `source` and standard eror trait
@@ -269,9 +1320,30 @@ pub mod serde_support {
         /// The file specified at this path does not exist.
         NoFile(std::path::PathBuf),
 
+        /// The document is not valid UTF-8, which TOML requires.
+        NotUtf8(std::str::Utf8Error),
+
+        /// The file at this path holds a single value or a list, instead of a TOML table or JSON
+        /// object of settings.
+        NotATable {
+            /// The path of the offending file.
+            path: std::path::PathBuf,
+        },
+
+        /// A `${VAR}` in one of the settings refers to an unset environment variable. Holds
+        /// [`crate::Error::MissingInterpolation`], which it is converted back into.
+        #[cfg(feature = "interpolate")]
+        Interpolation(Box<crate::Error>),
+
+        /// The document read through a [`DeserializerSource`] failed to deserialize.
+        Deserialize(Box<dyn std::error::Error + Send + Sync>),
+
         /// The file extension is not recognised.
         UnsupportedExtension(String),
 
+        /// The file extension is not valid Unicode, so it cannot be a supported extension.
+        NonUnicodeExtension(std::ffi::OsString),
+
         /// The file has no extension. While UNIX supports files without extension, we do not
         /// believe that this is either sound reasoning or useful for many users. Just add `.toml`
         /// or provide a custom implementation if you really need to use files without extensions.
@@ -284,6 +1356,24 @@ pub mod serde_support {
         }
     }
 
+    #[cfg(feature = "toml")]
+    impl From<toml::de::Error> for FileReadError {
+        fn from(value: toml::de::Error) -> Self {
+            Self::Toml(value)
+        }
+    }
+
+    impl FileReadError {
+        /// The contents of the file that failed to parse, if they were kept.
+        pub fn contents(&self) -> Option<&str> {
+            match self {
+                #[cfg(feature = "toml")]
+                Self::TomlFile { contents, .. } => Some(contents),
+                _ => None,
+            }
+        }
+    }
+
     impl core::fmt::Display for FileReadError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
             match self {
@@ -293,19 +1383,47 @@ pub mod serde_support {
                 Self::UnsupportedExtension(s) => {
                     write!(f, "The file extension {s} is not supported")
                 }
+                Self::NonUnicodeExtension(s) => {
+                    write!(f, "The file extension {s:?} is not valid Unicode")
+                }
                 Self::NoFile(path) => {
                     write!(f, "The file {path:?} could not be found")
                 }
+                Self::NotUtf8(err) => {
+                    write!(f, "The document is not valid UTF-8, because {err}")
+                }
+                Self::NotATable { path } => {
+                    write!(f, "The file {path:?} must contain settings as `key = value` pairs (TOML) or a `{{ \"key\": value }}` object (JSON), but contains a single value or a list")
+                }
                 Self::Open(err) => {
                     write!(f, "The file system reported the following error {err}")
                 }
                 #[cfg(feature = "toml")]
                 Self::Toml(te) => {
-                    write!(f, "Error parsing TOML file {te}")
+                    write!(
+                        f,
+                        "{}",
+                        crate::error_format::error_format().format_toml(te, None)
+                    )
+                }
+                #[cfg(feature = "toml")]
+                Self::TomlFile { error, contents } => {
+                    write!(
+                        f,
+                        "{}",
+                        crate::error_format::error_format().format_toml(error, Some(contents))
+                    )
                 }
                 #[cfg(feature = "json")]
                 Self::Json(je) => {
-                    write!(f, "Error parsing JSON file {je}")
+                    write!(f, "{}", crate::error_format::error_format().format_json(je))
+                }
+                Self::Deserialize(err) => {
+                    write!(f, "Error deserializing the document {err}")
+                }
+                #[cfg(feature = "interpolate")]
+                Self::Interpolation(err) => {
+                    write!(f, "{err}")
                 }
             }
         }
@@ -313,6 +1431,109 @@ pub mod serde_support {
 
     impl std::error::Error for FileReadError {}
 
+    /// Apply `patch` to `target` as described in RFC 7386.
+    #[cfg(feature = "json")]
+    pub(crate) fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+        let serde_json::Value::Object(patch) = patch else {
+            *target = patch;
+            return;
+        };
+        if !target.is_object() {
+            *target = serde_json::Value::Object(serde_json::Map::new());
+        }
+        let Some(object) = target.as_object_mut() else {
+            return;
+        };
+        for (key, value) in patch {
+            if value.is_null() {
+                object.remove(&key);
+            } else {
+                merge_patch(object.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+    }
+
+    /// Expand `${VAR}` in the string fields of a partial read from a document, if the
+    /// `interpolate` feature is enabled.
+    #[allow(clippy::unnecessary_wraps)]
+    fn interpolated<P: Partial>(partial: P) -> Result<P, FileReadError> {
+        #[cfg(feature = "interpolate")]
+        let partial = {
+            let mut partial = partial;
+            partial
+                .interpolate(&|var| std::env::var(var).ok())
+                .map_err(|err| FileReadError::Interpolation(Box::new(err)))?;
+            partial
+        };
+        Ok(partial)
+    }
+
+    /// Read a partial from a document in any `serde` data format, which is what all of the
+    /// `serde` sources come down to. The errors of the format are converted with `error`.
+    fn deserialize_partial<'de, C, D>(
+        deserializer: D,
+        error: impl FnOnce(D::Error) -> FileReadError,
+    ) -> Result<C::Partial, FileReadError>
+    where
+        C: HasPartial,
+        C::Partial: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        interpolated(serde::Deserialize::deserialize(deserializer).map_err(error)?)
+    }
+
+    /// Read a partial from a JSON document, rejecting trailing characters after it.
+    #[cfg(feature = "json")]
+    fn json_partial<'de, C, R>(
+        mut deserializer: serde_json::Deserializer<R>,
+    ) -> Result<C::Partial, FileReadError>
+    where
+        C: HasPartial,
+        C::Partial: serde::Deserialize<'de>,
+        R: serde_json::de::Read<'de>,
+    {
+        let partial = deserialize_partial::<C, _>(&mut deserializer, FileReadError::Json)?;
+        deserializer.end().map_err(FileReadError::Json)?;
+        Ok(partial)
+    }
+
+    /// A document in any `serde` data format, given as its deserializer, e.g. of `bincode`,
+    /// MessagePack or CBOR, for formats that have no dedicated source in this crate.
+    pub struct DeserializerSource<D>(pub D);
+
+    impl<'de, C, D> Source<C> for DeserializerSource<D>
+    where
+        C: HasPartial,
+        C::Partial: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+        D::Error: std::error::Error + Send + Sync + 'static,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            deserialize_partial::<C, _>(self.0, |err| FileReadError::Deserialize(Box::new(err)))
+        }
+
+        fn name(&self) -> String {
+            "Deserializer".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("Deserializer")
+        }
+    }
+
+    /// Deserialize a field marked `#[partial_nullable]`, such that an explicit `null` becomes
+    /// `Some(None)`, i.e. a request to clear the value set by a lower layer. Combined with
+    /// `#[serde(default)]`, which the derive macro adds for you, an absent key stays `None`.
+    pub fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+        T: serde::Deserialize<'de>,
+    {
+        serde::Deserialize::deserialize(deserializer).map(Some)
+    }
+
     #[cfg(feature = "toml")]
     /// This is a strongly typed file with the TOML format and extension. Used for type checking.
     pub struct Toml<'a>(pub &'a std::path::Path);
@@ -321,8 +1542,28 @@ pub mod serde_support {
     /// This is a strongly typed file with the JSON format and extension. Used for type checking.
     pub struct Json<'a>(pub &'a std::path::Path);
 
+    #[cfg(feature = "toml")]
+    /// A TOML document held in memory, e.g. defaults compiled into the binary with
+    /// `include_str!`.
+    pub struct InlineToml<'a>(pub &'a str);
+
     #[cfg(feature = "json")]
-    impl<'pth, C> Source<C> for Json<'pth>
+    /// A JSON document held in memory, e.g. defaults compiled into the binary with
+    /// `include_str!`.
+    pub struct InlineJson<'a>(pub &'a str);
+
+    #[cfg(feature = "toml")]
+    /// A TOML document held in memory as bytes, e.g. received over the network or embedded with
+    /// `include_bytes!`.
+    pub struct TomlBytes(pub Vec<u8>);
+
+    #[cfg(feature = "json")]
+    /// A JSON document read from any [`std::io::Read`], e.g. a network socket, without a path
+    /// on the file system.
+    pub struct JsonReader<R: std::io::Read>(pub R);
+
+    #[cfg(feature = "toml")]
+    impl<'src, C> Source<C> for InlineToml<'src>
     where
         C: HasPartial,
         C::Partial: serde::de::DeserializeOwned,
@@ -330,11 +1571,220 @@ pub mod serde_support {
         type Error = FileReadError;
 
         fn to_partial(self) -> Result<C::Partial, FileReadError> {
-            let Self(path) = self;
-            let file = std::fs::OpenOptions::new().read(true).open(path)?;
-            let partial: C::Partial = serde_json::from_reader(file).map_err(FileReadError::Json)?;
+            deserialize_partial::<C, _>(toml::Deserializer::new(self.0), FileReadError::from)
+        }
 
-            Ok(partial)
+        fn name(&self) -> String {
+            "Inline TOML defaults".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("Inline TOML defaults")
+        }
+    }
+
+    #[cfg(feature = "json")]
+    impl<'src, C> Source<C> for InlineJson<'src>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            json_partial::<C, _>(serde_json::Deserializer::from_str(self.0))
+        }
+
+        fn name(&self) -> String {
+            "Inline JSON defaults".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("Inline JSON defaults")
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    impl<C> Source<C> for TomlBytes
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            let contents = String::from_utf8(self.0)
+                .map_err(|err| FileReadError::NotUtf8(err.utf8_error()))?;
+            deserialize_partial::<C, _>(toml::Deserializer::new(&contents), FileReadError::from)
+        }
+
+        fn name(&self) -> String {
+            format!("TOML document of {} bytes", self.0.len())
+        }
+    }
+
+    #[cfg(feature = "json")]
+    impl<C, R> Source<C> for JsonReader<R>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+        R: std::io::Read,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            json_partial::<C, _>(serde_json::Deserializer::from_reader(self.0))
+        }
+
+        fn name(&self) -> String {
+            "JSON reader".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("JSON reader")
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    /// An already parsed TOML value, e.g. a section of a larger document handed to a plugin.
+    impl<C> Source<C> for toml::Value
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            self.try_into().map_err(FileReadError::from)
+        }
+
+        fn name(&self) -> String {
+            "TOML value".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("TOML value")
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    /// Settings given as `path.to.field=value` strings, e.g. by a `--set server.port=8080`
+    /// command line option. Each path selects a field, and the fields of flattened structures
+    /// are selected by the name of the flattened field followed by a dot. The values are read as
+    /// TOML values, e.g. `8080`, `true` or `[1, 2]`, and as plain strings if they are not valid
+    /// TOML, so that quotes are only needed for strings that look like other values, as in
+    /// `name="123"`. If a path is given more than once, the last value wins.
+    ///
+    /// The settings are collected into a TOML table, which is then deserialized like a TOML
+    /// file. The source therefore requires the `toml` feature and a partial that derives
+    /// `serde::Deserialize`, and a path names the key that `serde` reads, e.g. the name given by
+    /// `#[partial_only(serde(rename = "..."))]`. Fields that `serde` skips cannot be set.
+    pub struct OverrideSource(pub Vec<String>);
+
+    #[cfg(feature = "toml")]
+    impl<C> Source<C> for OverrideSource
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = crate::Error;
+
+        fn to_partial(self) -> Result<C::Partial, crate::Error> {
+            let mut document = toml::Table::new();
+            for setting in self.0 {
+                let invalid = || crate::Error::InvalidOverride {
+                    setting: setting.clone(),
+                };
+                let (path, value) = setting.split_once('=').ok_or_else(invalid)?;
+                let mut keys: Vec<&str> = path.trim().split('.').map(str::trim).collect();
+                let field = keys
+                    .pop()
+                    .filter(|key| !key.is_empty())
+                    .ok_or_else(invalid)?;
+                let mut table = &mut document;
+                for key in keys {
+                    if key.is_empty() {
+                        return Err(invalid());
+                    }
+                    table = table
+                        .entry(key)
+                        .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                        .as_table_mut()
+                        .ok_or_else(invalid)?;
+                }
+                let value = value.trim();
+                let parsed = <toml::Value as serde::Deserialize>::deserialize(
+                    toml::de::ValueDeserializer::new(value),
+                )
+                .unwrap_or_else(|_| toml::Value::String(value.to_owned()));
+                table.insert(field.to_owned(), parsed);
+            }
+            Ok(<toml::Value as Source<C>>::to_partial(toml::Value::Table(
+                document,
+            ))?)
+        }
+
+        fn name(&self) -> String {
+            format!("Overrides `{}`", self.0.join(" "))
+        }
+    }
+
+    #[cfg(feature = "json")]
+    /// An already parsed JSON value, e.g. a section of a larger document handed to a plugin.
+    impl<C> Source<C> for serde_json::Value
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            serde_json::from_value(self).map_err(FileReadError::Json)
+        }
+
+        fn name(&self) -> String {
+            "JSON value".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("JSON value")
+        }
+    }
+
+    #[cfg(feature = "config-compat")]
+    /// A configuration assembled with the `config` crate, so that an existing setup can be used as
+    /// one of the layers while migrating. Keys that do not belong to the partial are ignored, as
+    /// with the other `serde` sources.
+    impl<C> Source<C> for config::Config
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = config::ConfigError;
+
+        fn to_partial(self) -> Result<C::Partial, config::ConfigError> {
+            self.try_deserialize()
+        }
+
+        fn name(&self) -> String {
+            "`config` crate configuration".to_owned()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Borrowed("`config` crate configuration")
+        }
+    }
+
+    #[cfg(feature = "json")]
+    impl<'pth, C> Source<C> for Json<'pth>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            interpolated(json_file(self.0)?)
         }
 
         fn name(&self) -> String {
@@ -342,6 +1792,23 @@ pub mod serde_support {
         }
     }
 
+    /// Read the JSON file at `path` into `T`, which is either a partial or a document.
+    #[cfg(feature = "json")]
+    fn json_file<T: serde::de::DeserializeOwned>(
+        path: &std::path::Path,
+    ) -> Result<T, FileReadError> {
+        let contents = std::fs::read_to_string(path)?;
+        // Structures can be deserialized from JSON arrays, so a list at the top level is
+        // rejected before it can be mistaken for the fields in declaration order.
+        let is_object = contents.trim_start().is_empty() || contents.trim_start().starts_with('{');
+        if !is_object && serde_json::from_str::<serde_json::Value>(&contents).is_ok() {
+            return Err(FileReadError::NotATable {
+                path: path.to_path_buf(),
+            });
+        }
+        serde_json::from_str(&contents).map_err(FileReadError::Json)
+    }
+
     #[cfg(feature = "toml")]
     impl<'pth, C> Source<C> for Toml<'pth>
     where
@@ -355,9 +1822,73 @@ pub mod serde_support {
             let mut file = std::fs::OpenOptions::new().read(true).open(path)?;
             let mut buffer: String = String::new();
             file.read_to_string(&mut buffer)?;
-            let partial: C::Partial = toml::from_str(&buffer).map_err(FileReadError::Toml)?;
+            toml_file::<C>(&buffer, path)
+        }
 
-            Ok(partial)
+        fn name(&self) -> String {
+            format!("TOML file at {:?}", self.0)
+        }
+    }
+
+    /// Parse the `contents` of the TOML file at `path`.
+    #[cfg(feature = "toml")]
+    fn toml_file<C>(contents: &str, path: &std::path::Path) -> Result<C::Partial, FileReadError>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        interpolated(toml_document(contents, path)?)
+    }
+
+    /// Parse the `contents` of the TOML file at `path` into `T`, which is either a partial or a
+    /// document.
+    #[cfg(feature = "toml")]
+    fn toml_document<T: serde::de::DeserializeOwned>(
+        contents: &str,
+        path: &std::path::Path,
+    ) -> Result<T, FileReadError> {
+        serde::Deserialize::deserialize(toml::Deserializer::new(contents)).map_err(|err| {
+            // A lone value is a syntax error in TOML, which would be reported as such.
+            let value = toml::de::ValueDeserializer::new(contents.trim());
+            if <toml::Value as serde::Deserialize>::deserialize(value).is_ok() {
+                FileReadError::NotATable {
+                    path: path.to_path_buf(),
+                }
+            } else {
+                FileReadError::TomlFile {
+                    error: Box::new(err),
+                    contents: contents.to_owned(),
+                }
+            }
+        })
+    }
+
+    #[cfg(feature = "mmap")]
+    /// Same as [`Toml`], but the file is mapped into memory instead of being copied into a
+    /// buffer, e.g. for large generated configuration files that are reloaded frequently. The
+    /// file must not be modified while it is read.
+    pub struct TomlMmap<'a>(pub &'a std::path::Path);
+
+    #[cfg(feature = "mmap")]
+    impl<'pth, C> Source<C> for TomlMmap<'pth>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            let Self(path) = self;
+            let file = std::fs::File::open(path)?;
+            // An empty file cannot be mapped on all platforms, and is an empty layer anyway.
+            if file.metadata()?.len() == 0 {
+                return toml_file::<C>("", path);
+            }
+            // SAFETY: the mapping is only read while this function runs, and the documentation of
+            // `TomlMmap` requires that the file is not modified in the meantime.
+            let map = unsafe { memmap2::Mmap::map(&file)? };
+            let contents = std::str::from_utf8(&map).map_err(FileReadError::NotUtf8)?;
+            toml_file::<C>(contents, path)
         }
 
         fn name(&self) -> String {
@@ -365,6 +1896,58 @@ pub mod serde_support {
         }
     }
 
+    #[cfg(all(feature = "toml", feature = "toml_edit"))]
+    impl Toml<'_> {
+        /// The comment directly above the key at the dotted `field` path, e.g. `server.port`, with
+        /// the leading `#` removed, for a self-documenting `--explain port` subcommand. Returns
+        /// `None` if the file cannot be read or parsed, if the key is absent, or if the key has no
+        /// comment.
+        pub fn explain(&self, field: &str) -> Option<String> {
+            let contents = std::fs::read_to_string(self.0).ok()?;
+            let document: toml_edit::DocumentMut = contents.parse().ok()?;
+            let (parents, last) = match field.rsplit_once('.') {
+                Some((parents, last)) => (parents.split('.').collect(), last),
+                None => (Vec::new(), field),
+            };
+            let mut table: &dyn toml_edit::TableLike = document.as_table();
+            for segment in parents {
+                table = table.get(segment)?.as_table_like()?;
+            }
+            let (key, item) = table.get_key_value(last)?;
+            // The comments above a `[table]` header belong to the table, not to its key.
+            let prefix = match item {
+                toml_edit::Item::Table(table) => table.decor().prefix(),
+                _ => key.leaf_decor().prefix(),
+            }?
+            .as_str()?;
+            let comment: Vec<&str> = prefix
+                .lines()
+                .rev()
+                .map(str::trim)
+                .take_while(|line| line.starts_with('#'))
+                .map(|line| line.trim_start_matches('#').trim())
+                .collect();
+            if comment.is_empty() {
+                None
+            } else {
+                Some(comment.into_iter().rev().collect::<Vec<_>>().join("\n"))
+            }
+        }
+    }
+
+    /// The file extensions understood by the [`std::path::PathBuf`] source with the enabled
+    /// features.
+    const SUPPORTED_EXTENSIONS: &[&str] = &[
+        #[cfg(feature = "toml")]
+        "toml",
+        #[cfg(feature = "toml")]
+        "tml",
+        #[cfg(feature = "json")]
+        "json",
+        #[cfg(feature = "json")]
+        "js",
+    ];
+
     impl<C> Source<C> for std::path::PathBuf
     where
         C: HasPartial,
@@ -372,16 +1955,37 @@ pub mod serde_support {
     {
         type Error = FileReadError;
 
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            <&std::path::Path as Source<C>>::to_partial(&self)
+        }
+
+        fn name(&self) -> String {
+            format!("Configuration file at `{:?}`", self)
+        }
+    }
+
+    /// Same as the [`std::path::PathBuf`] source, for a path that is borrowed, e.g. from the
+    /// command line arguments.
+    impl<C> Source<C> for &std::path::Path
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
         fn to_partial(self) -> Result<C::Partial, FileReadError> {
             if !self.exists() {
-                Err(FileReadError::NoFile(self))
+                Err(FileReadError::NoFile(self.to_path_buf()))
             } else {
                 match self.extension() {
-                    Some(os_str) => match os_str.to_str().expect("Failed conversion from OsStr") {
+                    Some(os_str) => match os_str
+                        .to_str()
+                        .ok_or_else(|| FileReadError::NonUnicodeExtension(os_str.to_owned()))?
+                    {
                         #[cfg(feature = "toml")]
-                        "toml" | "tml" => <Toml<'_> as Source<C>>::to_partial(Toml(&self)),
+                        "toml" | "tml" => <Toml<'_> as Source<C>>::to_partial(Toml(self)),
                         #[cfg(feature = "json")]
-                        "json" | "js" => <Json<'_> as Source<C>>::to_partial(Json(&self)),
+                        "json" | "js" => <Json<'_> as Source<C>>::to_partial(Json(self)),
                         rest => Err(FileReadError::UnsupportedExtension(rest.to_owned())),
                     },
                     None => Err(FileReadError::NoExtension),
@@ -393,12 +1997,451 @@ pub mod serde_support {
             format!("Configuration file at `{:?}`", self)
         }
     }
+
+    /// A configuration file that is read if it exists, e.g. `/etc/my-app.toml`, and produces an
+    /// empty layer otherwise. A file that exists is read with the [`std::path::PathBuf`] source,
+    /// so errors reading or parsing it are still reported. Unlike `Option<PathBuf>`, this
+    /// expresses an optional file at a known path rather than an optional path.
+    pub struct OptionalFile<'a>(pub &'a std::path::Path);
+
+    impl<C> Source<C> for OptionalFile<'_>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            if self.0.exists() {
+                <&std::path::Path as Source<C>>::to_partial(self.0)
+            } else {
+                Ok(C::Partial::default())
+            }
+        }
+
+        fn name(&self) -> String {
+            format!("Optional configuration file at `{:?}`", self.0)
+        }
+    }
+
+    /// A directory of configuration fragments, e.g. `conf.d/`. Every file with a supported
+    /// extension is read with the [`std::path::PathBuf`] source, in lexical order of the file
+    /// names, and the resulting layers are combined with [`Partial::override_with`], so that the
+    /// later files take precedence. Files with unsupported or no extensions are skipped with a
+    /// warning, as are subdirectories.
+    pub struct ConfDir<'a>(pub &'a std::path::Path);
+
+    impl<'pth, C> Source<C> for ConfDir<'pth>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            let Self(directory) = self;
+            if !directory.exists() {
+                return Err(FileReadError::NoFile(directory.to_path_buf()));
+            }
+            let mut paths = std::fs::read_dir(directory)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.sort();
+
+            let mut partial = C::Partial::default();
+            for path in paths {
+                let supported = path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|extension| extension.to_str())
+                        .is_some_and(|extension| SUPPORTED_EXTENSIONS.contains(&extension));
+                if supported {
+                    partial =
+                        partial.override_with(<std::path::PathBuf as Source<C>>::to_partial(path)?);
+                } else {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(
+                        "Skipping {path:?}, which is not a supported configuration file"
+                    );
+                    #[cfg(feature = "log")]
+                    log::warn!("Skipping {path:?}, which is not a supported configuration file");
+                    #[cfg(not(any(feature = "log", feature = "tracing")))]
+                    crate::report::report(
+                        crate::report::Level::Warn,
+                        &format!("Skipping {path:?}, which is not a supported configuration file"),
+                    );
+                }
+            }
+            Ok(partial)
+        }
+
+        fn name(&self) -> String {
+            format!("Configuration directory at `{:?}`", self.0)
+        }
+    }
+
+    /// A source whose keys are renamed before they are matched to the fields of the partial, for
+    /// configuration files written for other programs, e.g. `[("listen_addr", "bind_address")]`
+    /// reads `listen_addr` into the `bind_address` field. Only the keys at the top level of the
+    /// document are renamed. If both the old and the new key are present, the renamed value
+    /// takes precedence.
+    pub struct RenamingSource<'a, S> {
+        /// The source of the document with the foreign keys, e.g. [`Toml`] or [`Json`].
+        pub source: S,
+        /// Pairs of the key in the document and the name of the field that it should be read
+        /// into.
+        pub renames: &'a [(&'a str, &'a str)],
+    }
+
+    impl<'a, S> RenamingSource<'a, S> {
+        /// Rename the keys of `source` according to `renames`.
+        pub fn new(source: S, renames: &'a [(&'a str, &'a str)]) -> Self {
+            Self { source, renames }
+        }
+    }
+
+    impl<C, S> Source<C> for RenamingSource<'_, S>
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+        S: document::DocumentSource + Source<C, Error = FileReadError>,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            interpolated(self.source.read_renamed(self.renames)?)
+        }
+
+        fn name(&self) -> String {
+            format!("{} with renamed keys", Source::<C>::name(&self.source))
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            std::borrow::Cow::Owned(format!(
+                "{} with renamed keys",
+                Source::<C>::source_kind(&self.source)
+            ))
+        }
+    }
+
+    mod document {
+        use super::FileReadError;
+
+        /// A source whose document can be read with its keys renamed, see
+        /// [`super::RenamingSource`]. Not meant to be implemented outside of this crate.
+        pub trait DocumentSource {
+            /// Read the document, move the value of each of the first keys of `renames` at the
+            /// top level to the second, and deserialize the result into `T`.
+            ///
+            /// # Errors
+            ///
+            /// - Same as the [`crate::Source`] implementation of the source.
+            fn read_renamed<T: serde::de::DeserializeOwned>(
+                self,
+                renames: &[(&str, &str)],
+            ) -> Result<T, FileReadError>;
+        }
+
+        /// Move the value of each of the first keys of `renames` in `table` to the second.
+        #[cfg(feature = "toml")]
+        fn rename_toml<T: serde::de::DeserializeOwned>(
+            mut table: toml::Table,
+            renames: &[(&str, &str)],
+        ) -> Result<T, FileReadError> {
+            for (from, to) in renames {
+                if let Some(value) = table.remove(*from) {
+                    table.insert((*to).to_owned(), value);
+                }
+            }
+            Ok(toml::Value::Table(table).try_into()?)
+        }
+
+        /// Move the value of each of the first keys of `renames` in `object` to the second.
+        #[cfg(feature = "json")]
+        fn rename_json<T: serde::de::DeserializeOwned>(
+            mut object: serde_json::Map<String, serde_json::Value>,
+            renames: &[(&str, &str)],
+        ) -> Result<T, FileReadError> {
+            for (from, to) in renames {
+                if let Some(value) = object.remove(*from) {
+                    object.insert((*to).to_owned(), value);
+                }
+            }
+            serde_json::from_value(serde_json::Value::Object(object)).map_err(FileReadError::Json)
+        }
+
+        #[cfg(feature = "toml")]
+        impl DocumentSource for super::Toml<'_> {
+            fn read_renamed<T: serde::de::DeserializeOwned>(
+                self,
+                renames: &[(&str, &str)],
+            ) -> Result<T, FileReadError> {
+                let contents = std::fs::read_to_string(self.0)?;
+                rename_toml(super::toml_document(&contents, self.0)?, renames)
+            }
+        }
+
+        #[cfg(feature = "toml")]
+        impl DocumentSource for super::InlineToml<'_> {
+            fn read_renamed<T: serde::de::DeserializeOwned>(
+                self,
+                renames: &[(&str, &str)],
+            ) -> Result<T, FileReadError> {
+                rename_toml(toml::from_str(self.0)?, renames)
+            }
+        }
+
+        #[cfg(feature = "json")]
+        impl DocumentSource for super::Json<'_> {
+            fn read_renamed<T: serde::de::DeserializeOwned>(
+                self,
+                renames: &[(&str, &str)],
+            ) -> Result<T, FileReadError> {
+                rename_json(super::json_file(self.0)?, renames)
+            }
+        }
+
+        #[cfg(feature = "json")]
+        impl DocumentSource for super::InlineJson<'_> {
+            fn read_renamed<T: serde::de::DeserializeOwned>(
+                self,
+                renames: &[(&str, &str)],
+            ) -> Result<T, FileReadError> {
+                rename_json(
+                    serde_json::from_str(self.0).map_err(FileReadError::Json)?,
+                    renames,
+                )
+            }
+        }
+    }
+
+    #[cfg(feature = "directories")]
+    /// A configuration file in the platform's standard configuration directory, e.g.
+    /// `$XDG_CONFIG_HOME/<app_name>/<file_stem>.toml` on Linux. The extensions of all enabled file
+    /// formats are tried in turn, and the first file that exists is read with the
+    /// [`std::path::PathBuf`] source. If no such file exists, an empty layer is produced instead
+    /// of an error, so that this source can be used as an optional base layer.
+    pub struct XdgSource {
+        /// The name of the application, which is the name of the directory inside the platform's
+        /// configuration directory.
+        pub app_name: String,
+        /// The name of the configuration file without the extension.
+        pub file_stem: String,
+    }
+
+    #[cfg(feature = "directories")]
+    impl XdgSource {
+        const EXTENSIONS: &'static [&'static str] = &[
+            #[cfg(feature = "toml")]
+            "toml",
+            #[cfg(feature = "json")]
+            "json",
+        ];
+
+        /// The first existing configuration file, if any.
+        pub fn find(&self) -> Option<std::path::PathBuf> {
+            let dirs = directories::ProjectDirs::from("", "", &self.app_name)?;
+            Self::EXTENSIONS
+                .iter()
+                .map(|extension| {
+                    dirs.config_dir()
+                        .join(&self.file_stem)
+                        .with_extension(extension)
+                })
+                .find(|path| path.exists())
+        }
+    }
+
+    #[cfg(feature = "directories")]
+    impl<C> Source<C> for XdgSource
+    where
+        C: HasPartial,
+        C::Partial: serde::de::DeserializeOwned,
+    {
+        type Error = FileReadError;
+
+        fn to_partial(self) -> Result<C::Partial, FileReadError> {
+            match self.find() {
+                Some(path) => <std::path::PathBuf as Source<C>>::to_partial(path),
+                None => Ok(C::Partial::default()),
+            }
+        }
+
+        fn name(&self) -> String {
+            match self.find() {
+                Some(path) => format!("Configuration file at `{path:?}`"),
+                None => format!(
+                    "Configuration directory for `{}` (no `{}` file found)",
+                    self.app_name, self.file_stem
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "notify")]
+pub mod watch;
+
+#[cfg(feature = "toml_edit")]
+pub mod edit;
+
+#[cfg(any(feature = "humantime", feature = "bytesize"))]
+pub mod units;
+
+#[cfg(feature = "std")]
+pub mod report;
+
+#[cfg(feature = "interpolate")]
+pub mod interpolate;
+
+#[cfg(all(feature = "serde", feature = "std"))]
+pub mod error_format;
+
+#[cfg(all(feature = "windows-registry", windows))]
+pub mod registry;
+
+#[cfg(feature = "eyre")]
+pub mod eyre_support {
+    use super::{HasPartial, Source};
+
+    /// A [`Source`] adapter that converts the error of the wrapped source into an
+    /// [`eyre::Report`]. This lets you mix file, environment and custom sources in a single
+    /// [`Partial::source`](super::Partial::source) chain, so long as the partial's error type
+    /// implements `From<eyre::Report>`, without writing a `From` impl for each source error.
+    pub struct Eyre<S>(pub S);
+
+    impl<C, S> Source<C> for Eyre<S>
+    where
+        C: HasPartial,
+        S: Source<C>,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        type Error = eyre::Report;
+
+        fn to_partial(self) -> Result<C::Partial, eyre::Report> {
+            self.0.to_partial().map_err(eyre::Report::new)
+        }
+
+        fn name(&self) -> String {
+            self.0.name()
+        }
+
+        fn source_kind(&self) -> std::borrow::Cow<'static, str> {
+            self.0.source_kind()
+        }
+    }
+
+    /// Extension trait providing [`IntoEyre::into_eyre`] on any value, most usefully on any
+    /// [`Source`].
+    pub trait IntoEyre: Sized {
+        /// Wrap `self` so that its [`Source::Error`] is reported as an [`eyre::Report`].
+        fn into_eyre(self) -> Eyre<Self> {
+            Eyre(self)
+        }
+    }
+
+    impl<T> IntoEyre for T {}
 }
 
 /// Implement this trait if you want to indicate that your structure
 /// can optionally contain a configuration path.
+#[cfg(feature = "std")]
 pub trait ConfigPath<T: AsRef<std::path::Path>> {
     /// Obtain a configuration path from `self`. Ideally you only want to consider things like
     /// `&str` but there can be other valid implementations.
     fn config_path(&self) -> Option<T>;
 }
+
+/// Resolve the configuration using the conventional two-stage bootstrap: the CLI arguments and
+/// the environment variables are consulted first to discover the path to the configuration file,
+/// which is then loaded as the base layer. The environment variables are applied on top of that,
+/// followed by the CLI arguments, so the final precedence is the same as
+/// ```text
+/// PartialConfiguration::default().source(file).source(env).source(cli)
+/// ```
+///
+/// If neither `cli` nor `env` specify a configuration path, the file layer is empty. A path that
+/// is specified but cannot be read is an error.
+#[cfg(all(feature = "serde", feature = "std"))]
+pub fn resolve_with_config_path<P, C, E>(
+    cli: C::Partial,
+    env: E,
+) -> Result<C, <C::Partial as Partial>::Error>
+where
+    P: AsRef<std::path::Path>,
+    C: HasPartial,
+    C::Partial: ConfigPath<P> + serde::de::DeserializeOwned,
+    E: Source<C>,
+    <C::Partial as Partial>::Error: From<E::Error> + From<crate::serde_support::FileReadError>,
+{
+    // The environment is read before the file, which it can point to, but applied after it.
+    let env = C::Partial::default().source(env)?;
+    let path: Option<std::path::PathBuf> = cli
+        .config_path()
+        .or_else(|| env.config_path())
+        .map(|path| path.as_ref().to_path_buf());
+    C::Partial::default()
+        .source(path)?
+        .override_with(env)
+        .override_with(cli)
+        .build()
+}
+
+/// A kind of layer passed to [`resolve_in_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerKind {
+    /// The configuration file.
+    File,
+    /// The environment variables.
+    Env,
+    /// The command line arguments.
+    Cli,
+}
+
+/// Resolve the configuration from the layers in `order`, from the lowest to the highest
+/// precedence, so that the precedence can be read from a deployment setting instead of being
+/// fixed by the order of the [`Partial::source`] calls. For example
+/// `[LayerKind::File, LayerKind::Env, LayerKind::Cli]` is the same as
+/// ```text
+/// PartialConfiguration::default().source(file).source(env).source(cli)
+/// ```
+///
+/// A layer that is not listed in `order` is not applied, and a layer that is listed more than once
+/// is only applied at its first position. If `file` is `None`, the file layer is empty.
+#[cfg(all(feature = "serde", feature = "std"))]
+pub fn resolve_in_order<C, E, L>(
+    order: &[LayerKind],
+    file: Option<std::path::PathBuf>,
+    env: E,
+    cli: L,
+) -> Result<C, <C::Partial as Partial>::Error>
+where
+    C: HasPartial,
+    C::Partial: serde::de::DeserializeOwned,
+    E: Source<C>,
+    L: Source<C>,
+    <C::Partial as Partial>::Error:
+        From<E::Error> + From<L::Error> + From<crate::serde_support::FileReadError>,
+{
+    let mut file = Some(file);
+    let mut env = Some(env);
+    let mut cli = Some(cli);
+    let mut partial = C::Partial::default();
+    for layer in order {
+        partial = match layer {
+            LayerKind::File => match file.take() {
+                Some(file) => partial.source(file)?,
+                None => partial,
+            },
+            LayerKind::Env => match env.take() {
+                Some(env) => partial.source(env)?,
+                None => partial,
+            },
+            LayerKind::Cli => match cli.take() {
+                Some(cli) => partial.source(cli)?,
+                None => partial,
+            },
+        };
+    }
+    partial.build()
+}
@@ -0,0 +1,188 @@
+//! Configuration stored in the Windows Registry, e.g. for a Windows service, which is usually
+//! configured through values under `HKEY_LOCAL_MACHINE\Software\MyApp` rather than through
+//! environment variables.
+//!
+//! The values are read with the [`EnvSourced`](crate::env::EnvSourced) machinery: the name of a
+//! registry value is one of the candidates given in `#[env(...)]`, and it is converted to the
+//! field type exactly as an environment variable would be. `REG_SZ` values are used as they are,
+//! `REG_EXPAND_SZ` values are expanded, and `REG_DWORD` and `REG_QWORD` values are rendered in
+//! decimal.
+
+use core::ffi::c_void;
+use std::env::VarError;
+use std::ffi::OsString;
+use std::os::windows::ffi::OsStringExt;
+
+use crate::env::PrefixedSource;
+use crate::{HasPartial, Source};
+
+/// The predefined key under which the [`RegistrySource::subkey`] is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hive {
+    /// `HKEY_LOCAL_MACHINE`, for the configuration of the machine, e.g. of a service.
+    LocalMachine,
+    /// `HKEY_CURRENT_USER`, for the configuration of the user running the application.
+    CurrentUser,
+}
+
+impl Hive {
+    fn handle(self) -> isize {
+        // The predefined keys are sign-extended to the width of a handle.
+        match self {
+            Hive::LocalMachine => 0x8000_0002_u32 as i32 as isize,
+            Hive::CurrentUser => 0x8000_0001_u32 as i32 as isize,
+        }
+    }
+}
+
+impl core::fmt::Display for Hive {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Hive::LocalMachine => write!(f, "HKEY_LOCAL_MACHINE"),
+            Hive::CurrentUser => write!(f, "HKEY_CURRENT_USER"),
+        }
+    }
+}
+
+/// A [`Source`] reading the values of the registry key `subkey` in `hive`. The values are looked
+/// up by the environment variable names of `source`, which is usually
+/// `<YourConfiguration as EnvSourced>::Source::default()`. A value that does not exist, or
+/// cannot be read, leaves the field unspecified.
+pub struct RegistrySource<S> {
+    /// The predefined key containing [`RegistrySource::subkey`].
+    pub hive: Hive,
+    /// The path of the key containing the values, e.g. `Software\MyApp`.
+    pub subkey: String,
+    /// The source determining the names of the values and how they are parsed.
+    pub source: S,
+}
+
+impl<S> RegistrySource<S> {
+    /// Read the values of `source` from `hive\subkey`.
+    pub fn new(hive: Hive, subkey: impl Into<String>, source: S) -> Self {
+        Self {
+            hive,
+            subkey: subkey.into(),
+            source,
+        }
+    }
+}
+
+impl<C, S> Source<C> for RegistrySource<S>
+where
+    C: HasPartial,
+    S: PrefixedSource<C>,
+{
+    type Error = S::Error;
+
+    fn to_partial(self) -> Result<C::Partial, Self::Error> {
+        let (hive, subkey) = (self.hive, self.subkey);
+        self.source
+            .to_partial_from(&|name| read_value(hive, &subkey, name))
+    }
+
+    fn name(&self) -> String {
+        format!("Registry key {}\\{}", self.hive, self.subkey)
+    }
+}
+
+const RRF_RT_REG_SZ: u32 = 0x0000_0002;
+const RRF_RT_REG_DWORD: u32 = 0x0000_0018;
+const RRF_RT_REG_QWORD: u32 = 0x0000_0048;
+const REG_SZ: u32 = 1;
+const REG_DWORD: u32 = 4;
+const REG_QWORD: u32 = 11;
+const ERROR_SUCCESS: i32 = 0;
+const ERROR_FILE_NOT_FOUND: i32 = 2;
+const ERROR_MORE_DATA: i32 = 234;
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn RegGetValueW(
+        hkey: isize,
+        lpsubkey: *const u16,
+        lpvalue: *const u16,
+        dwflags: u32,
+        pdwtype: *mut u32,
+        pvdata: *mut c_void,
+        pcbdata: *mut u32,
+    ) -> i32;
+}
+
+fn wide(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(Some(0)).collect()
+}
+
+/// Read the value `name` of `hive\subkey`, in the form of [`std::env::var`].
+fn read_value(hive: Hive, subkey: &str, name: &str) -> Result<String, VarError> {
+    let wide_subkey = wide(subkey);
+    let wide_name = wide(name);
+    let flags = RRF_RT_REG_SZ | RRF_RT_REG_DWORD | RRF_RT_REG_QWORD;
+    let mut data: Vec<u8> = Vec::new();
+    loop {
+        let mut kind = 0_u32;
+        let mut size = data.len() as u32;
+        // SAFETY: the key and value names are NUL-terminated, and `size` is the length of `data`.
+        let status = unsafe {
+            RegGetValueW(
+                hive.handle(),
+                wide_subkey.as_ptr(),
+                wide_name.as_ptr(),
+                flags,
+                &mut kind,
+                if data.is_empty() {
+                    core::ptr::null_mut()
+                } else {
+                    data.as_mut_ptr().cast()
+                },
+                &mut size,
+            )
+        };
+        match status {
+            // The first call only determines the size. The value may also grow in between.
+            ERROR_SUCCESS if data.is_empty() && size > 0 => data.resize(size as usize, 0),
+            ERROR_MORE_DATA => data.resize(size as usize, 0),
+            ERROR_SUCCESS => {
+                data.truncate(size as usize);
+                return decode(kind, &data);
+            }
+            ERROR_FILE_NOT_FOUND => return Err(VarError::NotPresent),
+            status => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Failed to read the registry value {name:?}: error {status}");
+                #[cfg(feature = "log")]
+                log::warn!("Failed to read the registry value {name:?}: error {status}");
+                #[cfg(not(any(feature = "tracing", feature = "log")))]
+                crate::report::report(
+                    crate::report::Level::Warn,
+                    &format!("Failed to read the registry value {name:?}: error {status}"),
+                );
+                return Err(VarError::NotPresent);
+            }
+        }
+    }
+}
+
+fn decode(kind: u32, data: &[u8]) -> Result<String, VarError> {
+    match kind {
+        REG_DWORD => {
+            let bytes: [u8; 4] = data.try_into().map_err(|_| VarError::NotPresent)?;
+            Ok(u32::from_le_bytes(bytes).to_string())
+        }
+        REG_QWORD => {
+            let bytes: [u8; 8] = data.try_into().map_err(|_| VarError::NotPresent)?;
+            Ok(u64::from_le_bytes(bytes).to_string())
+        }
+        REG_SZ => {
+            let mut text: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            while text.last() == Some(&0) {
+                text.pop();
+            }
+            String::from_utf16(&text).map_err(|_| VarError::NotUnicode(OsString::from_wide(&text)))
+        }
+        _ => Err(VarError::NotPresent),
+    }
+}
@@ -0,0 +1,42 @@
+//! The destination of the diagnostics of this crate, e.g. `Sourcing configuration from ...`, if
+//! neither the `tracing` nor the `log` feature is enabled. By default they are printed to the
+//! standard error, so that they do not mix with the output of tools whose standard output is
+//! piped. Applications can capture or suppress them with [`set_reporter`].
+
+use std::sync::OnceLock;
+
+/// The severity of a diagnostic, with the same meaning as in `log` and `tracing`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Something went wrong and a setting could not be used.
+    Error,
+    /// A setting was skipped or specified redundantly.
+    Warn,
+    /// A layer was read, or the configuration was built.
+    Info,
+}
+
+/// A function that receives every diagnostic, together with its severity.
+pub type Reporter = Box<dyn Fn(Level, &str) + Send + Sync>;
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+
+/// Send all further diagnostics to `reporter` instead of the standard error. A reporter that
+/// does nothing suppresses them. The reporter can only be set once, so that libraries cannot
+/// redirect the diagnostics of the application.
+///
+/// # Errors
+///
+/// - If a reporter has already been set, in which case `reporter` is handed back.
+pub fn set_reporter(reporter: Reporter) -> Result<(), Reporter> {
+    REPORTER.set(reporter)
+}
+
+/// Pass `message` to the reporter set with [`set_reporter`], or print it to the standard error.
+/// Used by this crate and the code generated by its derive macros.
+pub fn report(level: Level, message: &str) {
+    match REPORTER.get() {
+        Some(reporter) => reporter(level, message),
+        None => eprintln!("{message}"),
+    }
+}
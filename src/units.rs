@@ -0,0 +1,109 @@
+//! Parsers for values with human-readable units, such as `timeout = "1m 30s"` or
+//! `max_body = "8MiB"`, used by the `#[env_parse_duration]` and `#[env_parse_bytesize]` field
+//! attributes of the derive macros. The same attributes make file layers accept these strings, as
+//! well as plain numbers of seconds or bytes. Durations are parsed by `humantime` and sizes by
+//! `bytesize`, each enabled by the feature of the same name.
+
+#[cfg(feature = "humantime")]
+use core::time::Duration;
+
+/// A value with units failed to parse.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitError {
+    /// The duration was rejected by `humantime`.
+    #[cfg(feature = "humantime")]
+    Duration(humantime::DurationError),
+    /// The size was rejected by `bytesize`, which reports its errors as text.
+    #[cfg(feature = "bytesize")]
+    ByteSize(String),
+}
+
+impl core::fmt::Display for UnitError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "humantime")]
+            UnitError::Duration(err) => write!(f, "{err}"),
+            #[cfg(feature = "bytesize")]
+            UnitError::ByteSize(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// Parse a duration such as `30s`, `1h 30m` or `250ms` with [`humantime::parse_duration`]. A
+/// bare `0` is also accepted.
+///
+/// # Errors
+///
+/// - If the value is empty, or any of its parts is not a number followed by a known unit.
+#[cfg(feature = "humantime")]
+pub fn parse_duration(value: &str) -> Result<Duration, UnitError> {
+    humantime::parse_duration(value.trim()).map_err(UnitError::Duration)
+}
+
+/// Parse a number of bytes such as `512`, `8MiB` or `1.5 GB` with [`bytesize::ByteSize`]. The
+/// decimal units `kB`, `MB`, `GB`, `TB` and the binary units `KiB`, `MiB`, `GiB`, `TiB` are
+/// case-insensitive, and a number without a unit, or with `B`, is a number of bytes.
+///
+/// # Errors
+///
+/// - If the value is not a single number followed by a known unit.
+#[cfg(feature = "bytesize")]
+pub fn parse_bytesize(value: &str) -> Result<u64, UnitError> {
+    value
+        .trim()
+        .parse::<bytesize::ByteSize>()
+        .map(|size| size.as_u64())
+        .map_err(UnitError::ByteSize)
+}
+
+/// Deserialize a field marked `#[env_parse_duration]` from a string accepted by
+/// [`parse_duration`], or from a number of seconds.
+#[cfg(all(feature = "serde", feature = "humantime"))]
+pub fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer
+        .deserialize_any(UnitVisitor(parse_duration, Duration::from_secs))
+        .map(Some)
+}
+
+/// Deserialize a field marked `#[env_parse_bytesize]` from a string accepted by
+/// [`parse_bytesize`], or from a number of bytes.
+#[cfg(all(feature = "serde", feature = "bytesize"))]
+pub fn deserialize_bytesize<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer
+        .deserialize_any(UnitVisitor(parse_bytesize, |bytes| bytes))
+        .map(Some)
+}
+
+#[cfg(feature = "serde")]
+struct UnitVisitor<T>(fn(&str) -> Result<T, UnitError>, fn(u64) -> T);
+
+#[cfg(feature = "serde")]
+impl<T> serde::de::Visitor<'_> for UnitVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "a number, or a string with units")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<T, E> {
+        Ok((self.1)(value))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<T, E> {
+        u64::try_from(value)
+            .map(self.1)
+            .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<T, E> {
+        (self.0)(value).map_err(E::custom)
+    }
+}
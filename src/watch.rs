@@ -0,0 +1,123 @@
+//! Hot reloading of configuration files. A [`Watcher`] watches a single configuration file, and
+//! every time it changes, re-runs the full layer stack and delivers a freshly built configuration.
+
+use crate::serde_support::FileReadError;
+use crate::{HasPartial, Partial, Source};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// Watches a configuration file and delivers a freshly built configuration on every change.
+///
+/// The file is read with the [`std::path::PathBuf`] [`Source`], and the resulting layer is handed
+/// to the `stack` function provided to [`Watcher::new`], which should apply the remaining layers
+/// and build the configuration, e.g.
+/// ```text
+/// move |file| file.source(EnvVarSource::new())?.source(cli.clone())?.build()
+/// ```
+///
+/// Errors, whether from reading the file or from building the configuration, are delivered
+/// through the same channel as the configuration itself. They do not stop the watcher, so a
+/// transient bad edit does not take down a long-running service. The file is watched until the
+/// [`Watcher`] is dropped.
+pub struct Watcher<C: HasPartial> {
+    // Kept alive for as long as we want to receive events.
+    _watcher: notify::RecommendedWatcher,
+    receiver: mpsc::Receiver<Result<C, <C::Partial as Partial>::Error>>,
+}
+
+impl<C> Watcher<C>
+where
+    C: HasPartial + Send + 'static,
+    C::Partial: serde::de::DeserializeOwned,
+    <C::Partial as Partial>::Error: From<FileReadError> + Send + 'static,
+{
+    /// Start watching the file at `path`. The configuration is built once immediately, so the
+    /// first [`Watcher::recv`] returns the current configuration rather than waiting for a change.
+    ///
+    /// # Errors
+    ///
+    /// - If the directory containing the file cannot be watched.
+    pub fn new<F>(path: impl Into<PathBuf>, stack: F) -> Result<Self, notify::Error>
+    where
+        F: Fn(C::Partial) -> Result<C, <C::Partial as Partial>::Error> + Send + 'static,
+    {
+        use notify::Watcher as _;
+
+        let path: PathBuf = path.into();
+        let (sender, receiver) = mpsc::channel();
+        let reload = move |path: &Path| {
+            let result = <PathBuf as Source<C>>::to_partial(path.to_path_buf())
+                .map_err(Into::into)
+                .and_then(&stack);
+            // The receiving end is only dropped together with the watcher.
+            let _ = sender.send(result);
+        };
+        reload(&path);
+
+        let watched = path.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                // Only the parent directory is watched, so the file name is sufficient.
+                Ok(event)
+                    if is_change(&event)
+                        && event
+                            .paths
+                            .iter()
+                            .any(|p| p.file_name() == watched.file_name()) =>
+                {
+                    reload(&watched)
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("Failed to watch configuration file {watched:?}: {err}");
+                    #[cfg(feature = "log")]
+                    log::warn!("Failed to watch configuration file {watched:?}: {err}");
+                    #[cfg(not(any(feature = "log", feature = "tracing")))]
+                    crate::report::report(
+                        crate::report::Level::Warn,
+                        &format!("Failed to watch configuration file {watched:?}: {err}"),
+                    );
+                }
+            })?;
+        // Editors often replace the file rather than write to it, so the directory is watched
+        // instead of the file itself.
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        watcher.watch(directory, notify::RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+        })
+    }
+
+    /// Block until the next configuration (or error) is available.
+    pub fn recv(&self) -> Result<C, <C::Partial as Partial>::Error> {
+        self.receiver
+            .recv()
+            .expect("The watcher owns the sending half of the channel")
+    }
+
+    /// Wait for at most `timeout` for the next configuration (or error).
+    pub fn recv_timeout(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Option<Result<C, <C::Partial as Partial>::Error>> {
+        self.receiver.recv_timeout(timeout).ok()
+    }
+
+    /// Obtain the next configuration (or error) if one is available, without blocking.
+    pub fn try_recv(&self) -> Option<Result<C, <C::Partial as Partial>::Error>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+fn is_change(event: &notify::Event) -> bool {
+    matches!(
+        event.kind,
+        notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+    )
+}
@@ -1,4 +1,8 @@
-use partial_config::{EnvSourced, Error, HasPartial, Partial};
+use std::borrow::Cow;
+
+use partial_config::{
+    EnvSourced, Error, FieldChange, FieldIssue, HasPartial, Partial, Source, Unset,
+};
 
 pub struct Optional;
 
@@ -10,7 +14,7 @@ pub struct Conf {
     #[env(THING3)]
     pub str1: String,
 
-    #[env(THING3)]
+    #[env(THING5)]
     pub obj2: usize,
 
     #[env(THING4)]
@@ -28,6 +32,320 @@ pub struct Configuration {
     pub optional_field: Option<u64>,
 }
 
+#[derive(HasPartial)]
+#[partial_derives(Clone)]
+pub struct Timeouts {
+    #[partial_nullable]
+    pub timeout: Option<u64>,
+    pub retries: Option<u64>,
+}
+
+/// A command line source with a `--no-timeout` flag, clearing the timeout of the lower layers.
+pub struct NoTimeoutFlag(pub bool);
+
+impl Source<Timeouts> for NoTimeoutFlag {
+    type Error = Error;
+
+    fn to_partial(self) -> Result<PartialTimeouts, Self::Error> {
+        Ok(PartialTimeouts {
+            timeout: if self.0 { Unset.into() } else { None },
+            retries: None,
+        })
+    }
+
+    fn name(&self) -> String {
+        "--no-timeout".to_owned()
+    }
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Clone, Debug, PartialEq)]
+pub struct Layered {
+    pub required: u32,
+    pub optional: Option<u32>,
+    #[partial_nullable]
+    pub nullable: Option<u32>,
+}
+
+/// A layer that only sets the number of retries.
+pub struct RetriesLayer(pub u64);
+
+impl Source<Timeouts> for RetriesLayer {
+    type Error = Error;
+
+    fn to_partial(self) -> Result<PartialTimeouts, Self::Error> {
+        Ok(PartialTimeouts {
+            timeout: None,
+            retries: Some(self.0),
+        })
+    }
+
+    fn name(&self) -> String {
+        format!("{} retries", self.0)
+    }
+}
+
+#[derive(HasPartial)]
+#[partial_derives(Clone)]
+pub struct Borrowed<'a> {
+    pub name: &'a str,
+    pub alias: Option<&'a str>,
+}
+
+pub struct BorrowedSource<'a>(&'a str);
+
+impl<'a> partial_config::Source<Borrowed<'a>> for BorrowedSource<'a> {
+    type Error = Error;
+
+    fn to_partial(self) -> Result<PartialBorrowed<'a>, Self::Error> {
+        Ok(PartialBorrowed {
+            name: Some(self.0),
+            alias: None,
+        })
+    }
+
+    fn name(&self) -> String {
+        "Borrowed".to_owned()
+    }
+}
+
+#[derive(HasPartial)]
+#[partial_derives(Debug)]
+pub struct Described {
+    pub port: u16,
+    pub host: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ApplicationError(pub String);
+
+impl From<Error> for ApplicationError {
+    fn from(value: Error) -> Self {
+        Self(value.to_string())
+    }
+}
+
+#[derive(HasPartial)]
+#[partial_error(ApplicationError)]
+pub struct WithCustomError {
+    pub port: u16,
+}
+
+/// Deliberately neither `Default` nor cheap to construct by accident.
+#[derive(Debug)]
+pub struct Handle(pub u64);
+
+#[derive(HasPartial)]
+pub struct NoDefault {
+    pub handle: Handle,
+    pub port: u16,
+}
+
+pub fn non_empty(value: &str) -> Result<(), &'static str> {
+    if value.is_empty() {
+        Err("it is empty")
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(HasPartial)]
+pub struct Validated {
+    pub port: u16,
+    #[partial_validate(non_empty)]
+    pub host: String,
+}
+
+#[derive(HasPartial)]
+pub struct Tuning {
+    pub threads: Option<usize>,
+    #[partial_validate(non_empty)]
+    pub label: Option<String>,
+}
+
+#[derive(HasPartial)]
+pub struct Worker {
+    pub name: String,
+    #[partial_flatten]
+    pub tuning: Tuning,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[partial_derives(Debug)]
+#[env_var_rename(TlsEnv)]
+pub struct Tls {
+    #[env(CERT)]
+    pub cert: String,
+    #[env(KEY)]
+    pub key: Option<String>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[partial_derives(Debug)]
+#[env_var_rename(ServerEnv)]
+pub struct Server {
+    #[env(SERVER_PORT)]
+    pub port: u16,
+    #[partial_flatten]
+    pub tls: Tls,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(ListenerEnv)]
+pub struct Listener {
+    #[env(LISTENER_PORT)]
+    pub port: u16,
+}
+
+pub fn strip_quotes(value: &str) -> Result<String, std::convert::Infallible> {
+    Ok(value.trim_matches('"').to_owned())
+}
+
+pub fn legacy_port(value: &str) -> Result<u16, String> {
+    value
+        .strip_prefix("port:")
+        .and_then(|port| port.parse().ok())
+        .ok_or_else(|| format!("`{value}` is not of the form `port:<number>`"))
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(LegacyEnv)]
+pub struct Legacy {
+    #[env(LEGACY_TOKEN)]
+    #[env_parse_with(strip_quotes)]
+    pub token: String,
+    #[env(LEGACY_PORT)]
+    #[env_parse_with(legacy_port)]
+    pub port: Option<u16>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(StorageEnv)]
+pub struct Storage {
+    #[env(DATA_DIR)]
+    pub data: Option<std::path::PathBuf>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(DeploymentEnv)]
+#[env_profile_prefix(DEPLOY_PROFILE)]
+pub struct Deployment {
+    #[env(DEPLOY_PORT)]
+    pub port: u16,
+}
+
+/// Does not implement `FromStr`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Workers(pub u16);
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(PoolEnv)]
+pub struct Pool {
+    #[env(POOL_WORKERS)]
+    #[env_from_inner]
+    pub workers: Workers,
+    #[env(POOL_SPARE)]
+    #[env_from_inner]
+    pub spare: Option<Workers>,
+    #[env(POOL_IDLE, POOL_IDLE_SECONDS)]
+    #[env_empty_is_unset]
+    pub idle: Option<u64>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(BannerEnv)]
+pub struct Banner {
+    #[env(BANNER_TEXT)]
+    pub text: std::borrow::Cow<'static, str>,
+    #[env(BANNER_FOOTER)]
+    pub footer: Option<Cow<'static, str>>,
+}
+
+#[cfg(all(feature = "humantime", feature = "bytesize"))]
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(LimitsEnv)]
+pub struct Limits {
+    #[env(LIMITS_TIMEOUT)]
+    #[env_parse_duration]
+    pub timeout: std::time::Duration,
+    #[env(LIMITS_MAX_BODY)]
+    #[env_parse_bytesize]
+    pub max_body: Option<u64>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(EndpointsEnv)]
+pub struct Endpoints {
+    #[env(ENDPOINTS_PORTS)]
+    pub ports: Option<Vec<u16>>,
+    #[env(ENDPOINTS_HOSTS)]
+    #[env_separator(";")]
+    pub hosts: Vec<String>,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(DatabaseEnv)]
+pub struct Database {
+    #[env(DB_USER)]
+    #[env_file_fallback]
+    pub user: String,
+    #[env(DB_PASSWORD)]
+    #[env_file_fallback]
+    pub password: String,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[partial_derives(Debug)]
+#[env_var_rename(GatedEnv)]
+pub struct Gated {
+    #[env(GATED_PORT)]
+    pub port: u16,
+    #[cfg(feature = "toml")]
+    #[env(GATED_CERT)]
+    pub tls_cert: String,
+    #[cfg(not(feature = "toml"))]
+    #[env(GATED_PLAINTEXT)]
+    pub plaintext: Option<bool>,
+    #[cfg(feature = "toml")]
+    #[partial_flatten]
+    pub tls: Tls,
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug)]
+pub struct Quotas {
+    #[partial_default(64)]
+    pub connections: u32,
+    pub burst: Option<u32>,
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug)]
+pub struct Baked {
+    #[partial_default("localhost".to_owned())]
+    pub host: String,
+    #[partial_default(8080)]
+    pub port: u16,
+    pub name: String,
+    #[partial_nullable]
+    #[partial_default(30)]
+    pub timeout: Option<u64>,
+    #[partial_flatten]
+    pub quotas: Quotas,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(FallbackEnv)]
+pub struct Fallback {
+    #[env(FALLBACK_HOST, default = "localhost")]
+    pub host: String,
+    #[env(FALLBACK_PORT, default = 8080)]
+    pub port: u16,
+    #[env(FALLBACK_WORKERS, default = 4)]
+    pub workers: Option<usize>,
+}
+
 #[derive(Debug, Default)]
 pub struct EnvVarSomething;
 
@@ -135,19 +453,1309 @@ fn complete_config_overrides_correctly() {
         .build()
         .unwrap();
     assert_eq!(conf.optional_field, Some(42));
-    let conf = CustomPartialConfiguration::default()
+    let partial = CustomPartialConfiguration::default()
         .source(DefaultSource)
         .unwrap()
         .source(OptionalOnlySource)
-        .unwrap()
-        .clone()
-        .build()
         .unwrap();
+    let conf = partial.build_ref().unwrap();
     assert_eq!(conf.optional_field, Some(42_u64));
+    assert_eq!(partial.build_ref().unwrap().str1, conf.str1);
     assert_eq!(conf.clone().optional_field, Some(42_u64))
 }
 
 #[test]
+fn defaults_specify_the_annotated_fields() {
+    let defaults = PartialBaked::defaults();
+    assert_eq!(defaults.host.as_deref(), Some("localhost"));
+    assert_eq!(defaults.port, Some(8080));
+    assert_eq!(defaults.name, None);
+    assert_eq!(defaults.timeout, Some(Some(30)));
+    assert_eq!(defaults.quotas.connections, Some(64));
+    assert_eq!(defaults.quotas.burst, None);
+    assert_eq!(PartialBaked::default().port, None);
+
+    let baked = defaults
+        .override_with(PartialBaked {
+            name: Some("service".to_owned()),
+            port: Some(443),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    assert_eq!(baked.host, "localhost");
+    assert_eq!(baked.port, 443);
+    assert_eq!(baked.name, "service");
+    assert_eq!(baked.timeout, Some(30));
+    assert_eq!(baked.quotas.connections, 64);
+}
+
+#[test]
+fn merged_with_matches_override_with() {
+    let bottom = Str1OnlySource.to_partial().unwrap();
+    let top = OptionalOnlySource.to_partial().unwrap();
+    let merged = bottom.clone().merged_with(top.clone());
+    let overridden = bottom.override_with(top);
+    assert_eq!(merged.str1, overridden.str1);
+    assert_eq!(merged.optional_field, Some(42));
+    assert_eq!(overridden.optional_field, Some(42));
+}
+
+#[test]
+fn nullable_fields_distinguish_unset_from_cleared() {
+    let bottom = PartialTimeouts {
+        timeout: Some(Some(30)),
+        retries: Some(3),
+    };
+    let silent = PartialTimeouts::default();
+    let clearing = PartialTimeouts {
+        timeout: Some(None),
+        retries: None,
+    };
+
+    let conf = bottom.clone().override_with(silent).build().unwrap();
+    assert_eq!(conf.timeout, Some(30));
+    assert_eq!(conf.retries, Some(3));
+
+    let conf = bottom.override_with(clearing).build().unwrap();
+    assert_eq!(conf.timeout, None);
+    assert_eq!(conf.retries, Some(3));
+}
+
+#[test]
+fn sources_can_unset_nullable_fields() {
+    let bottom = PartialTimeouts {
+        timeout: Some(Some(30)),
+        retries: Some(3),
+    };
+    let conf = bottom
+        .clone()
+        .source(NoTimeoutFlag(false))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(conf.timeout, Some(30));
+
+    let conf = bottom.source(NoTimeoutFlag(true)).unwrap().build().unwrap();
+    assert_eq!(conf.timeout, None);
+    assert_eq!(conf.retries, Some(3));
+}
+
+#[test]
+fn partials_convert_with_try_into() -> Result<(), Error> {
+    let partial = PartialTimeouts {
+        timeout: Some(Some(30)),
+        retries: None,
+    };
+    let conf: Timeouts = partial.try_into()?;
+    assert_eq!(conf.timeout, Some(30));
+
+    let missing: Result<Validated, _> = PartialValidated::default().try_into();
+    assert!(matches!(missing, Err(Error::MissingFields { .. })));
+    Ok(())
+}
+
+#[test]
+fn profiled_stack_applies_the_active_profile() {
+    use partial_config::ProfiledStack;
+
+    let stack = || {
+        ProfiledStack::<Timeouts>::new()
+            .base(RetriesLayer(1))
+            .profile("production", RetriesLayer(5))
+            .profile("production", NoTimeoutFlag(true))
+            .profile("development", RetriesLayer(0))
+    };
+    let production = PartialTimeouts {
+        timeout: Some(Some(30)),
+        retries: None,
+    }
+    .override_with(stack().resolve("production").unwrap())
+    .build()
+    .unwrap();
+    assert_eq!(production.retries, Some(5));
+    assert_eq!(production.timeout, None);
+
+    let development = stack().resolve("development").unwrap().build().unwrap();
+    assert_eq!(development.retries, Some(0));
+
+    let default = stack().resolve("default").unwrap().build().unwrap();
+    assert_eq!(default.retries, Some(1));
+}
+
+/// A deterministic xorshift generator, so that the layers below are reproducible.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+fn random_value(state: &mut u64, absent: u64) -> Option<u32> {
+    (next_random(state) % 4 > absent).then(|| (next_random(state) % 100) as u32)
+}
+
+fn random_layer(state: &mut u64) -> PartialLayered {
+    PartialLayered {
+        required: random_value(state, 1),
+        optional: random_value(state, 1),
+        nullable: match next_random(state) % 3 {
+            0 => None,
+            1 => Some(None),
+            _ => Some(random_value(state, 0)),
+        },
+    }
+}
+
+#[test]
+fn override_with_takes_the_last_specified_value() {
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..500 {
+        let count = (next_random(&mut state) % 5) as usize;
+        let layers: Vec<PartialLayered> = (0..count).map(|_| random_layer(&mut state)).collect();
+
+        let folded = layers
+            .iter()
+            .cloned()
+            .fold(PartialLayered::default(), Partial::override_with);
+        let expected = PartialLayered {
+            required: layers.iter().rev().find_map(|layer| layer.required),
+            optional: layers.iter().rev().find_map(|layer| layer.optional),
+            nullable: layers.iter().rev().find_map(|layer| layer.nullable),
+        };
+        assert_eq!(folded, expected, "{layers:?}");
+
+        // Grouping the layers differently must not change the result.
+        if let [a, b, c, ..] = layers.as_slice() {
+            let left = a.clone().override_with(b.clone()).override_with(c.clone());
+            let right = a.clone().override_with(b.clone().override_with(c.clone()));
+            assert_eq!(left, right, "{layers:?}");
+        }
+
+        match (expected.required, folded.build()) {
+            (Some(required), Ok(conf)) => {
+                assert_eq!(conf.required, required);
+                assert_eq!(conf.optional, expected.optional);
+                assert_eq!(conf.nullable, expected.nullable.flatten());
+            }
+            (None, Err(Error::MissingFields { required_fields })) => {
+                assert_eq!(required_fields[0].path, "required");
+            }
+            (expected, built) => panic!("Expected {expected:?}, built {built:?}"),
+        }
+    }
+}
+
+#[test]
+fn merge_all_prefers_later_layers() {
+    let layers = vec![
+        PartialLayered {
+            required: Some(1),
+            optional: Some(1),
+            nullable: Some(Some(1)),
+        },
+        PartialLayered {
+            required: Some(2),
+            optional: None,
+            nullable: Some(None),
+        },
+        PartialLayered {
+            required: Some(3),
+            ..Default::default()
+        },
+    ];
+    assert_eq!(
+        PartialLayered::merge_all(layers),
+        PartialLayered {
+            required: Some(3),
+            optional: Some(1),
+            nullable: Some(None),
+        }
+    );
+    assert_eq!(
+        PartialLayered::merge_all(Vec::new()),
+        PartialLayered::default()
+    );
+}
+
+#[test]
+fn named_sources_report_their_label() {
+    use partial_config::NamedSource;
+
+    let source = NamedSource("site defaults".to_owned(), RetriesLayer(2));
+    assert_eq!(Source::<Timeouts>::name(&source), "site defaults");
+    let conf = PartialTimeouts::default()
+        .source(source)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(conf.retries, Some(2));
+}
+
+#[cfg(not(any(feature = "tracing", feature = "log", feature = "quiet")))]
+#[test]
+fn diagnostics_go_to_the_reporter() {
+    use partial_config::report::Level;
+    use partial_config::NamedSource;
+    use std::sync::{Arc, Mutex};
+
+    let reported: Arc<Mutex<Vec<(Level, String)>>> = Arc::default();
+    let sink = Arc::clone(&reported);
+    partial_config::set_reporter(Box::new(move |level, message| {
+        sink.lock().unwrap().push((level, message.to_owned()));
+    }))
+    .unwrap_or_else(|_| panic!("The reporter is only set by this test"));
+    assert!(partial_config::set_reporter(Box::new(|_, _| {})).is_err());
+
+    PartialTimeouts::default()
+        .source(NamedSource("reported layer".to_owned(), RetriesLayer(2)))
+        .unwrap();
+    assert!(reported.lock().unwrap().contains(&(
+        Level::Info,
+        "Sourcing configuration from `reported layer`".to_owned()
+    )));
+}
+
+#[test]
+fn borrowed_fields_round_trip() {
+    let owned = String::from("not static");
+    let conf = PartialBorrowed::default()
+        .source(BorrowedSource(&owned))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(conf.name, "not static");
+    assert_eq!(conf.alias, None);
+}
+
+#[test]
+fn describe_marks_missing_fields() {
+    let partial = PartialDescribed {
+        port: Some(8080),
+        host: None,
+        name: None,
+    };
+    assert_eq!(
+        partial.describe(),
+        "port = 8080\nhost = <missing>\nname = <default>"
+    );
+}
+
+#[test]
+fn render_effective_lists_resolved_fields() {
+    let server = PartialServer::default()
+        .with_port(443)
+        .with_tls(PartialTls::default().with_cert("server.pem".to_owned()))
+        .build()
+        .unwrap();
+    assert_eq!(
+        server.render_effective(),
+        "port = 443\ntls.cert = \"server.pem\"\ntls.key = None"
+    );
+}
+
+#[test]
+fn custom_error_type_reports_missing_fields() {
+    let Err(ApplicationError(message)) = PartialWithCustomError::default().build() else {
+        panic!("This should have missing fields!");
+    };
+    assert!(message.contains("port"), "{message}");
+}
+
+#[test]
+fn diff_reports_changed_fields_only() {
+    let base = PartialDescribed {
+        port: Some(80),
+        host: Some("localhost".to_owned()),
+        name: None,
+    };
+    let layer = PartialDescribed {
+        port: Some(8080),
+        host: Some("localhost".to_owned()),
+        name: None,
+    };
+    assert_eq!(
+        base.diff(&layer),
+        vec![FieldChange {
+            field: "port",
+            from: Some("80".to_owned()),
+            to: Some("8080".to_owned()),
+        }]
+    );
+    assert!(layer.diff(&layer).is_empty());
+    assert_eq!(
+        base.diff(&PartialDescribed::default())
+            .iter()
+            .map(|change| change.field)
+            .collect::<Vec<_>>(),
+        vec!["port", "host"]
+    );
+}
+
+#[test]
+fn required_fields_need_not_be_default() {
+    let Err(Error::MissingFields { required_fields }) = PartialNoDefault {
+        handle: None,
+        port: Some(80),
+    }
+    .build() else {
+        panic!("This should have missing fields!");
+    };
+    assert_eq!(required_fields.len(), 1);
+    assert_eq!(required_fields[0].path, "handle");
+
+    let conf = PartialNoDefault {
+        handle: Some(Handle(7)),
+        port: Some(80),
+    }
+    .build()
+    .unwrap();
+    assert_eq!(conf.handle.0, 7);
+}
+
+#[test]
+fn missing_and_invalid_fields_are_reported_together() {
+    let Err(Error::FieldIssues { issues }) = PartialValidated {
+        port: None,
+        host: Some(String::new()),
+    }
+    .build() else {
+        panic!("This should have invalid fields!");
+    };
+    assert_eq!(issues.len(), 2);
+    assert!(matches!(&issues[0], FieldIssue::Missing(field) if field.path == "port"));
+    assert!(matches!(
+        &issues[1],
+        FieldIssue::Invalid { field: "host", .. }
+    ));
+
+    let Err(Error::MissingFields { required_fields }) = PartialValidated {
+        port: None,
+        host: Some("localhost".to_owned()),
+    }
+    .build() else {
+        panic!("This should have missing fields only!");
+    };
+    assert_eq!(required_fields.len(), 1);
+}
+
+#[test]
+fn all_optional_structures_always_build() {
+    let tuning = PartialTuning::default().build().unwrap();
+    assert_eq!(tuning.threads, None);
+    assert_eq!(tuning.label, None);
+
+    let Err(Error::FieldIssues { issues }) = PartialTuning {
+        threads: Some(4),
+        label: Some(String::new()),
+    }
+    .build() else {
+        panic!("The label should be invalid!");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(matches!(
+        &issues[0],
+        FieldIssue::Invalid { field: "label", .. }
+    ));
+}
+
+#[test]
+fn invalid_flattened_fields_are_reported_without_the_missing_ones() {
+    let Err(Error::FieldIssues { issues }) = PartialWorker {
+        name: None,
+        tuning: PartialTuning {
+            threads: None,
+            label: Some(String::new()),
+        },
+    }
+    .build() else {
+        panic!("The label should be invalid!");
+    };
+    assert_eq!(issues.len(), 1);
+    assert!(matches!(
+        &issues[0],
+        FieldIssue::Invalid { field: "label", .. }
+    ));
+
+    let Err(Error::MissingFields { required_fields }) = PartialWorker::default().build() else {
+        panic!("The name should be missing!");
+    };
+    assert_eq!(required_fields[0].path, "name");
+}
+
+#[test]
+fn flattened_fields_are_prefixed_and_nested() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let env: EnvSnapshot = [("SERVER_PORT", "443"), ("TLS_CERT", "server.pem")]
+        .into_iter()
+        .collect();
+    let partial = ServerEnv::new()
+        .to_partial_from(&|key| env.var(key))
+        .unwrap();
+    assert_eq!(partial.tls.cert.as_deref(), Some("server.pem"));
+    assert_eq!(partial.tls.key, None);
+
+    let conf = partial
+        .override_with(PartialServer {
+            port: None,
+            tls: PartialTls {
+                cert: None,
+                key: Some("server.key".to_owned()),
+            },
+        })
+        .build()
+        .unwrap();
+    assert_eq!(conf.port, 443);
+    assert_eq!(conf.tls.cert, "server.pem");
+    assert_eq!(conf.tls.key.as_deref(), Some("server.key"));
+
+    let Err(err) = PartialServer::default().build() else {
+        panic!("This should have missing fields!");
+    };
+    assert!(err.to_string().contains("[port: u16, tls.cert: String]"));
+    let Error::MissingFields { required_fields } = err else {
+        panic!("This should have missing fields!");
+    };
+    assert_eq!(
+        required_fields
+            .iter()
+            .map(|field| (field.path.as_ref(), field.type_name))
+            .collect::<Vec<_>>(),
+        vec![("port", "u16"), ("tls.cert", "String")]
+    );
+}
+
+#[test]
+fn missing_fields_say_where_they_can_be_set() {
+    let Err(Error::MissingFields { required_fields }) = PartialServer::default().build() else {
+        panic!("This should have missing fields!");
+    };
+    assert_eq!(
+        required_fields
+            .iter()
+            .map(|field| (field.file_key.as_ref(), field.env_vars.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("port", vec!["SERVER_PORT".to_owned()]),
+            ("tls.cert", vec!["TLS_CERT".to_owned()])
+        ]
+    );
+    assert_eq!(
+        required_fields[1].to_string(),
+        "The field tls.cert of type `String` is missing, set it with `tls.cert` in a file or one of the environment variables TLS_CERT"
+    );
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(DottedEnv)]
+pub struct Dotted {
+    #[env("legacy.port", "LEGACY-PORT", LEGACY_PORT)]
+    pub port: u16,
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(TracingEnv)]
+pub struct Tracing {
+    #[env(TRACING_DEBUG, TRACING_VERBOSE, flag)]
+    pub debug: bool,
+}
+
+#[test]
+fn env_flags_are_set_by_presence() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let debug = |vars: &[(&str, &str)]| {
+        let env: EnvSnapshot = vars.iter().copied().collect();
+        TracingEnv::new()
+            .to_partial_from(&|key| env.var(key))
+            .unwrap()
+            .debug
+    };
+    assert_eq!(debug(&[]), None);
+    assert_eq!(debug(&[("TRACING_DEBUG", "")]), Some(true));
+    assert_eq!(
+        debug(&[("TRACING_DEBUG", ""), ("TRACING_VERBOSE", "yes")]),
+        Some(true)
+    );
+}
+
+#[test]
+fn env_names_can_be_string_literals() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let port = |name: &str, value: &str| {
+        let env: EnvSnapshot = [(name, value)].into_iter().collect();
+        DottedEnv::new()
+            .to_partial_from(&|key| env.var(key))
+            .unwrap()
+            .port
+    };
+    assert_eq!(port("legacy.port", "8080"), Some(8080));
+    assert_eq!(port("LEGACY-PORT", "8081"), Some(8081));
+
+    let Err(Error::MissingFields { required_fields }) = PartialDotted::default().build() else {
+        panic!("This should have missing fields!");
+    };
+    assert_eq!(
+        required_fields[0].env_vars,
+        ["legacy.port", "LEGACY-PORT", "LEGACY_PORT"]
+    );
+}
+
+#[test]
+fn integer_parse_errors_keep_their_type() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let env: EnvSnapshot = [("LISTENER_PORT", "99999")].into_iter().collect();
+    let result = ListenerEnv::new().to_partial_from(&|key| env.var(key));
+    let Err(Error::ParseIntFieldError { error, .. }) = result else {
+        panic!("The port should fail to parse!");
+    };
+    assert_eq!(error.kind(), &std::num::IntErrorKind::PosOverflow);
+}
+
+#[test]
+fn integer_parse_errors_name_the_field_and_value() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let env: EnvSnapshot = [("LISTENER_PORT", "eighty")].into_iter().collect();
+    let Err(err) = ListenerEnv::new().to_partial_from(&|key| env.var(key)) else {
+        panic!("The port should fail to parse!");
+    };
+    assert!(err.to_string().contains("The field port"));
+    assert!(err.to_string().contains("from value \"eighty\""));
+    assert!(matches!(
+        err,
+        Error::ParseIntFieldError {
+            field_name: "port",
+            ref value,
+            ..
+        } if value == "eighty"
+    ));
+}
+
+#[test]
+fn env_values_can_be_parsed_with_a_custom_function() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let env: EnvSnapshot = [("LEGACY_TOKEN", "\"secret\""), ("LEGACY_PORT", "port:8080")]
+        .into_iter()
+        .collect();
+    let legacy = LegacyEnv::new()
+        .to_partial_from(&|key| env.var(key))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(legacy.token, "secret");
+    assert_eq!(legacy.port, Some(8080));
+
+    let env: EnvSnapshot = [("LEGACY_PORT", "8080")].into_iter().collect();
+    let Err(err) = LegacyEnv::new().to_partial_from(&|key| env.var(key)) else {
+        panic!("The port should fail to parse!");
+    };
+    assert!(matches!(
+        &err,
+        Error::ParseFieldError {
+            field_name: "port",
+            value,
+            ..
+        } if value == "8080"
+    ));
+    assert!(err.to_string().contains("from value \"8080\""));
+}
+
+#[test]
+fn borrowed_sources_can_be_layered_repeatedly() {
+    let source = Str1OnlySource;
+    let first = CustomPartialConfiguration::default()
+        .source_ref(&source)
+        .unwrap();
+    let second = CustomPartialConfiguration {
+        port: Some(1),
+        ..Default::default()
+    }
+    .source_ref(&source)
+    .unwrap();
+    assert_eq!(first.str1, Some("CustomStruct"));
+    assert_eq!(second.str1, Some("CustomStruct"));
+    assert_eq!(second.port, Some(1));
+}
+
+#[test]
+fn set_fields_render_the_specified_values() {
+    let server = PartialServer {
+        port: Some(443),
+        tls: PartialTls {
+            cert: Some("server.pem".to_owned()),
+            key: None,
+        },
+    };
+    assert_eq!(
+        server.tls.set_fields().collect::<Vec<_>>(),
+        vec![("cert", "\"server.pem\"".to_owned())]
+    );
+    assert_eq!(
+        server
+            .set_fields()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+        vec!["port", "tls"]
+    );
+    assert_eq!(PartialTls::default().set_fields().count(), 0);
+}
+
+#[test]
+fn field_names_are_exposed() {
+    assert_eq!(PartialConf::FIELD_NAMES, &["str1", "obj2", "option"]);
+    assert_eq!(PartialConf::REQUIRED_FIELD_NAMES, &["str1", "obj2"]);
+    assert_eq!(PartialServer::REQUIRED_FIELD_NAMES, &["port"]);
+}
+
+#[test]
+fn env_defaults_complete_the_env_layer() {
+    use partial_config::env::PrefixedSource;
+
+    let fallback = FallbackEnv::new()
+        .to_partial_from(&|_| Err(std::env::VarError::NotPresent))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(fallback.host, "localhost");
+    assert_eq!(fallback.port, 8080);
+    assert_eq!(fallback.workers, Some(4));
+
+    let lookup = |key: &str| match key {
+        "FALLBACK_PORT" => Ok("443".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let fallback = FallbackEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(fallback.port, 443);
+}
+
+#[test]
+fn cfg_gated_fields_follow_the_structure() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "GATED_PORT" => Ok("443".to_owned()),
+        "GATED_CERT" => Ok("server.pem".to_owned()),
+        "GATED_PLAINTEXT" => Ok("true".to_owned()),
+        "TLS_CERT" => Ok("ca.pem".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let partial = GatedEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(
+        partial.diff(&PartialGated::default()).len(),
+        partial.set_fields().count()
+    );
+    let gated = partial
+        .override_with(PartialGated::default())
+        .build()
+        .unwrap();
+    assert_eq!(gated.port, 443);
+
+    #[cfg(feature = "toml")]
+    {
+        assert_eq!(PartialGated::FIELD_NAMES, &["port", "tls_cert", "tls"]);
+        assert_eq!(PartialGated::REQUIRED_FIELD_NAMES, &["port", "tls_cert"]);
+        assert_eq!(gated.tls_cert, "server.pem");
+        assert_eq!(gated.tls.cert, "ca.pem");
+        let missing = PartialGated {
+            port: Some(443),
+            ..Default::default()
+        }
+        .build();
+        let Err(Error::MissingFields { required_fields }) = missing else {
+            panic!("Expected the gated fields to be missing");
+        };
+        assert_eq!(required_fields.len(), 2);
+    }
+    #[cfg(not(feature = "toml"))]
+    {
+        assert_eq!(PartialGated::FIELD_NAMES, &["port", "plaintext"]);
+        assert_eq!(PartialGated::REQUIRED_FIELD_NAMES, &["port"]);
+        assert_eq!(gated.plaintext, Some(true));
+    }
+}
+
+#[test]
+fn env_sources_can_read_from_a_map() {
+    use partial_config::env::PrefixedSource;
+
+    let vars: std::collections::HashMap<&str, &str> = [
+        ("SERVER_PORT", "8443"),
+        ("TLS_CERT", "map.pem"),
+        ("TLS_KEY", "map.key"),
+    ]
+    .into();
+    let lookup = |key: &str| {
+        vars.get(key)
+            .map(|value| value.to_string())
+            .ok_or(std::env::VarError::NotPresent)
+    };
+    let server = ServerEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 8443);
+    assert_eq!(server.tls.cert, "map.pem");
+    assert_eq!(server.tls.key.as_deref(), Some("map.key"));
+
+    assert_eq!(
+        partial_config::env::extract_from(&["TLS_KEY", "UNSET"], &lookup).unwrap(),
+        Some("map.key".to_owned())
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn non_unicode_paths_are_read_from_env() {
+    use partial_config::env::PrefixedSource;
+    use std::os::unix::ffi::OsStringExt;
+
+    let raw = b"/srv/\xffdata".to_vec();
+    let lookup = |key: &str| match key {
+        "DATA_DIR" => Err(std::env::VarError::NotUnicode(
+            std::ffi::OsString::from_vec(raw.clone()),
+        )),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let storage = StorageEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(storage.data.unwrap().into_os_string().into_vec(), raw);
+}
+
+#[test]
+fn env_profile_selects_the_prefix() {
+    use partial_config::env::{PrefixedSource, Profile};
+
+    let vars: std::collections::HashMap<&str, &str> = [
+        ("DEPLOY_PROFILE", "prod"),
+        ("DEPLOY_PORT", "1"),
+        ("PROD_DEPLOY_PORT", "443"),
+        ("DEV_DEPLOY_PORT", "8080"),
+        ("DEV_SERVER_PORT", "8443"),
+        ("DEV_TLS_CERT", "dev.pem"),
+    ]
+    .into();
+    let lookup = |key: &str| {
+        vars.get(key)
+            .map(|value| value.to_string())
+            .ok_or(std::env::VarError::NotPresent)
+    };
+    let deployment = DeploymentEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(deployment.port, 443);
+
+    let unset = |key: &str| match key {
+        "DEPLOY_PORT" => Ok("1".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let deployment = DeploymentEnv::new()
+        .to_partial_from(&unset)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(deployment.port, 1);
+
+    let server = Profile::new("dev", ServerEnv::new())
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 8443);
+    assert_eq!(server.tls.cert, "dev.pem");
+}
+
+#[test]
+fn newtypes_are_parsed_from_their_inner_type() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "POOL_WORKERS" => Ok("8".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let pool = PoolEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(pool.workers, Workers(8));
+    assert_eq!(pool.spare, None);
+
+    let lookup = |key: &str| match key {
+        "POOL_WORKERS" => Ok("many".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    assert!(matches!(
+        PoolEnv::new().to_partial_from(&lookup),
+        Err(Error::ParseFieldError {
+            field_name: "workers",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn empty_env_values_can_mean_unset() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "POOL_WORKERS" => Ok("8".to_owned()),
+        "POOL_IDLE" => Ok(String::new()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let pool = PoolEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(pool.idle, None);
+
+    let lookup = |key: &str| match key {
+        "POOL_IDLE" => Ok(String::new()),
+        "POOL_IDLE_SECONDS" => Ok("30".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let pool = PoolEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(pool.idle, Some(30));
+
+    let lookup = |key: &str| match key {
+        "POOL_SPARE" => Ok(String::new()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    assert!(PoolEnv::new().to_partial_from(&lookup).is_err());
+}
+
+#[test]
+fn env_lists_are_split() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "ENDPOINTS_PORTS" => Ok("80, 443".to_owned()),
+        "ENDPOINTS_HOSTS" => Ok("localhost;example.com".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let endpoints = EndpointsEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(endpoints.ports, Some(vec![80, 443]));
+    assert_eq!(endpoints.hosts, ["localhost", "example.com"]);
+
+    let lookup = |key: &str| match key {
+        "ENDPOINTS_HOSTS" => Ok(String::new()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let endpoints = EndpointsEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(endpoints.ports, None);
+    assert_eq!(endpoints.hosts, Some(Vec::new()));
+
+    let lookup = |key: &str| match key {
+        "ENDPOINTS_PORTS" => Ok("80,http".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    assert!(matches!(
+        EndpointsEnv::new().to_partial_from(&lookup),
+        Err(Error::ParseFieldError {
+            field_name: "ports",
+            value,
+            ..
+        }) if value == "80,http"
+    ));
+}
+
+#[test]
+fn env_values_can_be_read_from_files() {
+    use partial_config::env::PrefixedSource;
+
+    let dir = std::env::temp_dir().join(format!("partial_config_secrets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let secret = dir.join("db_password");
+    std::fs::write(&secret, "hunter2\n").unwrap();
+    let path = secret.to_str().unwrap().to_owned();
+
+    let lookup = |key: &str| match key {
+        "DB_USER" => Ok("admin".to_owned()),
+        "DB_USER_FILE" | "DB_PASSWORD_FILE" => Ok(path.clone()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let database = DatabaseEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(database.user, "admin");
+    assert_eq!(database.password, "hunter2");
+
+    let lookup = |key: &str| match key {
+        "DB_PASSWORD_FILE" => Ok(dir.join("missing").to_str().unwrap().to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let database = DatabaseEnv::new().to_partial_from(&lookup).unwrap();
+    assert_eq!(database.password, None);
+}
+
+#[test]
+fn cow_fields_hold_the_env_value() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "BANNER_TEXT" => Ok("Welcome".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let banner = PartialBanner {
+        footer: Some(Cow::Borrowed("Default footer")),
+        ..Default::default()
+    }
+    .override_with(BannerEnv::new().to_partial_from(&lookup).unwrap())
+    .build()
+    .unwrap();
+    assert!(matches!(banner.text, Cow::Owned(ref text) if text == "Welcome"));
+    assert_eq!(banner.footer.as_deref(), Some("Default footer"));
+}
+
+#[cfg(all(feature = "humantime", feature = "bytesize"))]
+#[test]
+fn humanized_units_are_parsed() {
+    use partial_config::units::{parse_bytesize, parse_duration, UnitError};
+    use std::time::Duration;
+
+    assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration("1h 30m"), Ok(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+    assert_eq!(parse_duration("0"), Ok(Duration::ZERO));
+    assert!(matches!(parse_duration("30"), Err(UnitError::Duration(_))));
+    assert!(matches!(
+        parse_duration("3 fortnights"),
+        Err(UnitError::Duration(_))
+    ));
+
+    assert_eq!(parse_bytesize("512"), Ok(512));
+    assert_eq!(parse_bytesize("8MiB"), Ok(8 << 20));
+    assert_eq!(parse_bytesize("1.5 GB"), Ok(1_500_000_000));
+    assert_eq!(parse_bytesize("2kib"), Ok(2048));
+    assert!(matches!(parse_bytesize(""), Err(UnitError::ByteSize(_))));
+}
+
+#[cfg(all(feature = "humantime", feature = "bytesize"))]
+#[test]
+fn env_values_with_units_are_parsed() {
+    use partial_config::env::PrefixedSource;
+
+    let lookup = |key: &str| match key {
+        "LIMITS_TIMEOUT" => Ok("1m30s".to_owned()),
+        "LIMITS_MAX_BODY" => Ok("8MiB".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    let limits = LimitsEnv::new()
+        .to_partial_from(&lookup)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(limits.timeout, std::time::Duration::from_secs(90));
+    assert_eq!(limits.max_body, Some(8 << 20));
+
+    let lookup = |key: &str| match key {
+        "LIMITS_TIMEOUT" => Ok("soon".to_owned()),
+        _ => Err(std::env::VarError::NotPresent),
+    };
+    assert!(matches!(
+        LimitsEnv::new().to_partial_from(&lookup),
+        Err(Error::ParseFieldError {
+            field_name: "timeout",
+            ..
+        })
+    ));
+}
+
+#[test]
+#[allow(clippy::default_constructed_unit_structs)]
 fn rename_works() {
     EnvVarSomething::default();
 }
+
+#[cfg(feature = "eyre")]
+#[test]
+fn eyre_adapter_converts_source_errors() {
+    use partial_config::eyre_support::IntoEyre;
+
+    let conf = CustomPartialConfiguration::default()
+        .source(Str1OnlySource.into_eyre())
+        .unwrap()
+        .source(DefaultSource.into_eyre())
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(conf.str1, "");
+}
+
+#[cfg(feature = "async")]
+pub struct SecretStore;
+
+#[cfg(feature = "async")]
+impl partial_config::AsyncSource<Configuration> for SecretStore {
+    type Error = Error;
+
+    async fn to_partial(self) -> Result<CustomPartialConfiguration, Self::Error> {
+        Ok(CustomPartialConfiguration {
+            port: Some(8443),
+            height: Some(None),
+            custom_struct: Some(Str1OnlySource),
+            ..Default::default()
+        })
+    }
+
+    fn name(&self) -> String {
+        "Secret store".to_owned()
+    }
+}
+
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    loop {
+        if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_sources_mix_with_sync_ones() {
+    let conf = block_on(async {
+        CustomPartialConfiguration::default()
+            .source_async(Str1OnlySource)
+            .await?
+            .source_async(SecretStore)
+            .await?
+            .build()
+    })
+    .unwrap();
+    assert_eq!(conf.str1, "CustomStruct");
+    assert_eq!(conf.port, 8443);
+}
+
+#[test]
+fn setters_specify_single_fields() {
+    let partial = CustomPartialConfiguration::default()
+        .with_str1("fluent")
+        .with_port(8080)
+        .with_optional_field(7);
+    assert_eq!(partial.str1, Some("fluent"));
+    assert_eq!(partial.port, Some(8080));
+    assert_eq!(partial.optional_field, Some(7));
+    assert_eq!(partial.height, None);
+
+    let timeouts = PartialTimeouts::default().with_timeout(30).with_retries(3);
+    assert_eq!(timeouts.timeout, Some(Some(30)));
+    assert_eq!(timeouts.retries, Some(3));
+
+    let server = PartialServer::default()
+        .with_port(443)
+        .with_tls(PartialTls::default().with_cert("server.pem".to_owned()));
+    assert_eq!(server.port, Some(443));
+    assert_eq!(server.tls.cert.as_deref(), Some("server.pem"));
+    assert_eq!(server.tls.key, None);
+}
+
+#[test]
+fn env_snapshots_are_not_affected_by_later_changes() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    std::env::set_var("FALLBACK_HOST", "before");
+    let snapshot = EnvSnapshot::capture();
+    std::env::set_var("FALLBACK_HOST", "after");
+    assert_eq!(snapshot.var("FALLBACK_HOST").as_deref(), Ok("before"));
+    assert_eq!(
+        EnvSnapshot::capture().var("FALLBACK_HOST").as_deref(),
+        Ok("after")
+    );
+    std::env::remove_var("FALLBACK_HOST");
+
+    let snapshot: EnvSnapshot = [("FALLBACK_PORT", "443")].into_iter().collect();
+    let fallback = FallbackEnv::new()
+        .to_partial_from(&|key| snapshot.var(key))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(fallback.host, "localhost");
+    assert_eq!(fallback.port, 443);
+}
+
+mod endpoint {
+    use partial_config::HasPartial;
+
+    #[derive(HasPartial)]
+    #[partial_builder(Endpoint::from_parts)]
+    pub struct Endpoint {
+        host: String,
+        port: u16,
+        path: Option<String>,
+    }
+
+    impl Endpoint {
+        pub fn from_parts(host: String, port: u16, path: Option<String>) -> Self {
+            Self {
+                host: host.to_lowercase(),
+                port,
+                path,
+            }
+        }
+
+        pub fn url(&self) -> String {
+            format!(
+                "http://{}:{}/{}",
+                self.host,
+                self.port,
+                self.path.as_deref().unwrap_or_default()
+            )
+        }
+    }
+}
+
+#[test]
+fn builders_construct_targets_with_private_fields() {
+    let endpoint = endpoint::PartialEndpoint::default()
+        .with_host("Example.COM".to_owned())
+        .with_port(8080)
+        .build()
+        .unwrap();
+    assert_eq!(endpoint.url(), "http://example.com:8080/");
+
+    let err = endpoint::PartialEndpoint::default()
+        .with_host("example.com".to_owned())
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::MissingFields { .. }));
+}
+
+#[test]
+fn runtime_defaults_fill_the_unspecified_fields() {
+    let defaults = || Server {
+        port: 8080,
+        tls: Tls {
+            cert: "default.pem".to_owned(),
+            key: Some("default.key".to_owned()),
+        },
+    };
+    let server = PartialServer::default()
+        .with_tls(PartialTls::default().with_cert("server.pem".to_owned()))
+        .build_with_defaults(defaults())
+        .unwrap();
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.tls.cert, "server.pem");
+    assert_eq!(server.tls.key.as_deref(), Some("default.key"));
+
+    let timeouts = PartialTimeouts::default()
+        .with_retries(5)
+        .build_with_defaults(Timeouts {
+            timeout: Some(30),
+            retries: Some(3),
+        })
+        .unwrap();
+    assert_eq!(timeouts.timeout, Some(30));
+    assert_eq!(timeouts.retries, Some(5));
+}
+
+#[test]
+fn fixed_source_kinds_are_borrowed() {
+    assert!(matches!(
+        Source::<Server>::source_kind(&ServerEnv::new()),
+        Cow::Borrowed("Environment Variables")
+    ));
+    assert_eq!(
+        Source::<Configuration>::source_kind(&Str1OnlySource),
+        Source::<Configuration>::name(&Str1OnlySource)
+    );
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug)]
+pub struct Deferred {
+    pub port: u16,
+    #[partial_raw]
+    pub tls: PartialTls,
+}
+
+#[test]
+fn raw_partials_are_merged_but_not_built() {
+    let base = PartialDeferred::default()
+        .with_port(443)
+        .with_tls(PartialTls::default().with_cert("server.pem".to_owned()));
+    let layer = PartialDeferred::default()
+        .with_tls(PartialTls::default().with_key("server.key".to_owned()));
+    let deferred = base.override_with(layer).build().unwrap();
+    assert_eq!(deferred.port, 443);
+    assert_eq!(deferred.tls.cert.as_deref(), Some("server.pem"));
+    assert_eq!(deferred.tls.key.as_deref(), Some("server.key"));
+
+    let incomplete = PartialDeferred::default().with_port(80).build().unwrap();
+    assert!(incomplete.tls.build().is_err());
+    assert_eq!(PartialDeferred::REQUIRED_FIELD_NAMES, &["port"]);
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[partial_derives(Debug)]
+#[env_var_rename(JobRunnerEnv)]
+pub struct JobRunner {
+    #[env(JOB_RUNNER_THREADS)]
+    pub threads: usize,
+    #[partial_skip(default = std::time::Instant::now())]
+    pub started: std::time::Instant,
+    #[partial_skip]
+    pub queue: Vec<String>,
+}
+
+#[test]
+fn skipped_fields_are_filled_in_by_build() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let before = std::time::Instant::now();
+    let env: EnvSnapshot = [("JOB_RUNNER_THREADS", "4")].into_iter().collect();
+    let runner = JobRunnerEnv::new()
+        .to_partial_from(&|key| env.var(key))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(runner.threads, 4);
+    assert!(runner.started >= before);
+    assert!(runner.queue.is_empty());
+    assert_eq!(PartialJobRunner::FIELD_NAMES, &["threads"]);
+}
+
+#[derive(Debug, HasPartial, EnvSourced)]
+#[env_var_rename(MonorepoEnv)]
+pub struct Monorepo {
+    #[env(ordered(MONOREPO_SERVICE_PORT, MONOREPO_SHARED_PORT))]
+    pub port: u16,
+}
+
+#[test]
+fn ordered_env_candidates_prefer_the_first() {
+    use partial_config::env::{EnvSnapshot, PrefixedSource};
+
+    let port = |vars: &[(&str, &str)]| {
+        let env: EnvSnapshot = vars.iter().copied().collect();
+        MonorepoEnv::new()
+            .to_partial_from(&|key| env.var(key))
+            .unwrap()
+            .port
+    };
+    assert_eq!(port(&[("MONOREPO_SHARED_PORT", "8080")]), Some(8080));
+    assert_eq!(
+        port(&[
+            ("MONOREPO_SHARED_PORT", "8080"),
+            ("MONOREPO_SERVICE_PORT", "9090")
+        ]),
+        Some(9090)
+    );
+}
@@ -0,0 +1,1172 @@
+#![cfg(any(feature = "toml", feature = "json"))]
+
+use partial_config::{HasPartial, Partial};
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Server {
+    pub host: String,
+    pub port: u16,
+    pub name: Option<String>,
+}
+
+fn scratch_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir()
+        .join("partial_config_tests")
+        .join(format!("{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[cfg(feature = "directories")]
+#[test]
+fn xdg_source_reads_first_existing_file_or_nothing() {
+    use partial_config::serde_support::XdgSource;
+
+    let home = scratch_dir("xdg");
+    std::env::set_var("XDG_CONFIG_HOME", &home);
+    std::fs::create_dir_all(home.join("my-app")).unwrap();
+    std::fs::write(
+        home.join("my-app").join("server.toml"),
+        "host = \"localhost\"\nport = 8080\n",
+    )
+    .unwrap();
+
+    let server = PartialServer::default()
+        .source(XdgSource {
+            app_name: "my-app".to_owned(),
+            file_stem: "server".to_owned(),
+        })
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+
+    let empty = PartialServer::default()
+        .source(XdgSource {
+            app_name: "my-app".to_owned(),
+            file_stem: "absent".to_owned(),
+        })
+        .unwrap();
+    assert!(empty.host.is_none());
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(serde::Deserialize)]
+pub struct Client {
+    #[partial_nullable]
+    pub timeout: Option<u64>,
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_null_clears_nullable_field() {
+    use partial_config::serde_support::Json;
+
+    let dir = scratch_dir("nullable");
+    let base = dir.join("base.json");
+    let null = dir.join("null.json");
+    let empty = dir.join("empty.json");
+    std::fs::write(&base, r#"{"timeout": 30}"#).unwrap();
+    std::fs::write(&null, r#"{"timeout": null}"#).unwrap();
+    std::fs::write(&empty, "{}").unwrap();
+
+    let kept = PartialClient::default()
+        .source(Json(&base))
+        .unwrap()
+        .source(Json(&empty))
+        .unwrap();
+    assert_eq!(kept.timeout, Some(Some(30)));
+
+    let cleared = kept.source(Json(&null)).unwrap();
+    assert_eq!(cleared.timeout, Some(None));
+    assert_eq!(cleared.build().unwrap().timeout, None);
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+#[partial_deny_unknown]
+pub struct Strict {
+    pub port: u16,
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn deny_unknown_rejects_typos() {
+    use partial_config::serde_support::{FileReadError, Toml};
+
+    let dir = scratch_dir("deny_unknown");
+    let typo = dir.join("typo.toml");
+    std::fs::write(&typo, "prot = 8080\n").unwrap();
+
+    let err = PartialStrict::default().source(Toml(&typo)).unwrap_err();
+    match err {
+        partial_config::Error::FileReadError(err @ FileReadError::TomlFile { .. }) => {
+            assert!(err.to_string().contains("prot"), "{err}");
+        }
+        other => panic!("Expected a TOML error, got {other:?}"),
+    }
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn toml_errors_keep_the_file_contents() {
+    use partial_config::serde_support::{FileReadError, Toml};
+
+    let dir = scratch_dir("toml_contents");
+    let path = dir.join("typo.toml");
+    std::fs::write(&path, "# Listener\nprot = 8080\n").unwrap();
+
+    let err = <Toml as partial_config::Source<Strict>>::to_partial(Toml(&path)).unwrap_err();
+    let FileReadError::TomlFile { error, contents } = &err else {
+        panic!("Expected a TOML error, got {err:?}");
+    };
+    assert_eq!(err.contents(), Some("# Listener\nprot = 8080\n"));
+    assert_eq!(
+        partial_config::error_format::toml_location(error, contents),
+        Some((2, 1, "prot = 8080"))
+    );
+    assert!(
+        err.to_string()
+            .contains("line 2, column 1\n2 | prot = 8080\n  | ^\n"),
+        "{err}"
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn typos_in_keys_come_with_a_suggestion() {
+    use partial_config::error_format::suggestion;
+    use partial_config::serde_support::InlineToml;
+
+    let err = PartialStrict::default()
+        .source(InlineToml("prot = 8080\n"))
+        .unwrap_err();
+    assert!(err.to_string().contains("did you mean `port`?"), "{err}");
+
+    assert_eq!(
+        suggestion("unknown field `hots`, expected one of `host`, `port`"),
+        Some("host")
+    );
+    assert_eq!(
+        suggestion("unknown field `timeout`, expected one of `host`, `port`"),
+        None
+    );
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn top_level_values_are_not_tables() {
+    use partial_config::serde_support::FileReadError;
+    use partial_config::Source;
+
+    let dir = scratch_dir("not_a_table");
+    for (name, contents) in [
+        ("bare.toml", "8080\n"),
+        ("list.toml", "[\"localhost\", 8080]\n"),
+        ("list.json", "[\"localhost\", 8080]\n"),
+        ("bare.json", "\"localhost\"\n"),
+    ] {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        match <std::path::PathBuf as Source<Server>>::to_partial(path.clone()) {
+            Err(FileReadError::NotATable { path: reported }) => assert_eq!(reported, path),
+            other => panic!("Expected {name} not to be a table, got {other:?}"),
+        }
+    }
+
+    let path = dir.join("broken.toml");
+    std::fs::write(&path, "port = \n").unwrap();
+    assert!(matches!(
+        <std::path::PathBuf as Source<Server>>::to_partial(path),
+        Err(FileReadError::TomlFile { .. })
+    ));
+    let path = dir.join("broken.json");
+    std::fs::write(&path, "{\"port\": }\n").unwrap();
+    assert!(matches!(
+        <std::path::PathBuf as Source<Server>>::to_partial(path),
+        Err(FileReadError::Json(_))
+    ));
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Bootstrapped {
+    pub host: String,
+    pub port: u16,
+    pub config: Option<std::path::PathBuf>,
+}
+
+impl partial_config::ConfigPath<std::path::PathBuf> for PartialBootstrapped {
+    fn config_path(&self) -> Option<std::path::PathBuf> {
+        self.config.clone()
+    }
+}
+
+pub struct BootstrapEnv(PartialBootstrapped);
+
+impl partial_config::Source<Bootstrapped> for BootstrapEnv {
+    type Error = partial_config::Error;
+
+    fn to_partial(self) -> Result<PartialBootstrapped, Self::Error> {
+        Ok(self.0)
+    }
+
+    fn name(&self) -> String {
+        "Bootstrap environment".to_owned()
+    }
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn config_path_is_discovered_then_overridden() {
+    let dir = scratch_dir("bootstrap");
+    let file = dir.join("bootstrap.toml");
+    std::fs::write(&file, "host = \"file\"\nport = 80\n").unwrap();
+
+    let cli = PartialBootstrapped {
+        port: Some(8080),
+        ..Default::default()
+    };
+    let env = BootstrapEnv(PartialBootstrapped {
+        host: Some("env".to_owned()),
+        config: Some(file.clone()),
+        ..Default::default()
+    });
+    let conf: Bootstrapped = partial_config::resolve_with_config_path(cli, env).unwrap();
+    assert_eq!(conf.host, "env");
+    assert_eq!(conf.port, 8080);
+    assert_eq!(conf.config, Some(file));
+
+    let cli = PartialBootstrapped {
+        config: Some(dir.join("missing.toml")),
+        ..Default::default()
+    };
+    let env = BootstrapEnv(PartialBootstrapped::default());
+    assert!(partial_config::resolve_with_config_path::<_, Bootstrapped, _>(cli, env).is_err());
+}
+
+#[cfg(not(any(feature = "tracing", feature = "log", feature = "quiet")))]
+#[test]
+fn config_path_discovery_reports_the_environment_layer() {
+    use std::sync::{Arc, Mutex};
+
+    let reported: Arc<Mutex<Vec<String>>> = Arc::default();
+    let sink = Arc::clone(&reported);
+    partial_config::set_reporter(Box::new(move |_, message| {
+        sink.lock().unwrap().push(message.to_owned());
+    }))
+    .unwrap_or_else(|_| panic!("The reporter is only set by this test"));
+
+    let cli = PartialBootstrapped {
+        host: Some("cli".to_owned()),
+        port: Some(8080),
+        ..Default::default()
+    };
+    let env = BootstrapEnv(PartialBootstrapped::default());
+    partial_config::resolve_with_config_path::<_, Bootstrapped, _>(cli, env).unwrap();
+    assert!(reported
+        .lock()
+        .unwrap()
+        .contains(&"Sourcing configuration from `Bootstrap environment`".to_owned()));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn layers_are_applied_in_the_declared_order() {
+    use partial_config::LayerKind;
+
+    let dir = scratch_dir("ordered");
+    let file = dir.join("ordered.toml");
+    std::fs::write(&file, "host = \"file\"\nport = 80\n").unwrap();
+    let env = || {
+        BootstrapEnv(PartialBootstrapped {
+            host: Some("env".to_owned()),
+            port: Some(8000),
+            ..Default::default()
+        })
+    };
+    let cli = || {
+        BootstrapEnv(PartialBootstrapped {
+            port: Some(8080),
+            ..Default::default()
+        })
+    };
+
+    let conf: Bootstrapped = partial_config::resolve_in_order(
+        &[LayerKind::File, LayerKind::Env, LayerKind::Cli],
+        Some(file.clone()),
+        env(),
+        cli(),
+    )
+    .unwrap();
+    assert_eq!((conf.host.as_str(), conf.port), ("env", 8080));
+
+    let conf: Bootstrapped = partial_config::resolve_in_order(
+        &[LayerKind::Cli, LayerKind::Env, LayerKind::File],
+        Some(file.clone()),
+        env(),
+        cli(),
+    )
+    .unwrap();
+    assert_eq!((conf.host.as_str(), conf.port), ("file", 80));
+
+    let conf: Bootstrapped =
+        partial_config::resolve_in_order(&[LayerKind::Env, LayerKind::File], None, env(), cli())
+            .unwrap();
+    assert_eq!((conf.host.as_str(), conf.port), ("env", 8000));
+}
+
+#[cfg(all(feature = "notify", feature = "toml"))]
+#[test]
+fn watcher_delivers_reloads_and_errors() {
+    use partial_config::watch::Watcher;
+    use std::time::Duration;
+
+    let dir = scratch_dir("watch");
+    let file = dir.join("server.toml");
+    std::fs::write(&file, "host = \"localhost\"\nport = 80\n").unwrap();
+
+    let watcher: Watcher<Server> = Watcher::new(&file, |file: PartialServer| {
+        file.override_with(PartialServer {
+            name: Some("from the stack".to_owned()),
+            ..Default::default()
+        })
+        .build()
+    })
+    .unwrap();
+    let initial = watcher.recv().unwrap();
+    assert_eq!(initial.port, 80);
+    assert_eq!(initial.name.as_deref(), Some("from the stack"));
+
+    // Wait for the first event that matches, as editors and file systems may emit several.
+    let next = |predicate: &dyn Fn(&Result<Server, partial_config::Error>) -> bool| {
+        while let Some(result) = watcher.recv_timeout(Duration::from_secs(10)) {
+            if predicate(&result) {
+                return result;
+            }
+        }
+        panic!("The watcher did not deliver the expected reload");
+    };
+
+    std::fs::write(&file, "host = \"localhost\"\nport = \"eighty\"\n").unwrap();
+    assert!(next(&|result| result.is_err()).is_err());
+
+    std::fs::write(&file, "host = \"localhost\"\nport = 8080\n").unwrap();
+    let reloaded = next(&|result| result.is_ok()).unwrap();
+    assert_eq!(reloaded.port, 8080);
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Editable {
+    pub host: String,
+    pub port: u16,
+    pub name: Option<String>,
+}
+
+#[cfg(feature = "toml_edit")]
+#[test]
+fn write_partial_toml_preserves_comments() {
+    use partial_config::edit::write_partial_toml;
+
+    let dir = scratch_dir("edit");
+    let file = dir.join("editable.toml");
+    std::fs::write(
+        &file,
+        "# The host to bind to\nhost = \"localhost\"\n\n# The port\nport = 80 # privileged\n",
+    )
+    .unwrap();
+
+    write_partial_toml(
+        &file,
+        &PartialEditable {
+            host: None,
+            port: Some(8080),
+            name: Some("edited".to_owned()),
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&file).unwrap(),
+        "# The host to bind to\nhost = \"localhost\"\n\n# The port\nport = 8080 # privileged\nname = \"edited\"\n"
+    );
+}
+
+#[cfg(all(feature = "toml", feature = "toml_edit"))]
+#[test]
+fn toml_explain_returns_the_leading_comment() {
+    use partial_config::serde_support::Toml;
+
+    let dir = scratch_dir("explain");
+    let file = dir.join("explained.toml");
+    std::fs::write(
+        &file,
+        "# Unrelated\n\n# The host to bind to\n#   on every interface\nhost = \"0.0.0.0\"\nport = 80\n\n# Logging\n[log]\n# The verbosity\nlevel = \"info\"\n",
+    )
+    .unwrap();
+
+    let toml = Toml(&file);
+    assert_eq!(
+        toml.explain("host").as_deref(),
+        Some("The host to bind to\non every interface")
+    );
+    assert_eq!(toml.explain("port"), None);
+    assert_eq!(toml.explain("log").as_deref(), Some("Logging"));
+    assert_eq!(toml.explain("log.level").as_deref(), Some("The verbosity"));
+    assert_eq!(toml.explain("missing"), None);
+    assert_eq!(Toml(&dir.join("missing.toml")).explain("host"), None);
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn conf_dir_merges_fragments_in_lexical_order() {
+    use partial_config::serde_support::ConfDir;
+
+    let dir = scratch_dir("conf.d");
+    std::fs::write(dir.join("10-base.toml"), "host = \"base\"\nport = 80\n").unwrap();
+    std::fs::write(dir.join("20-port.json"), r#"{"port": 8080}"#).unwrap();
+    std::fs::write(dir.join("30-host.toml"), "host = \"override\"\n").unwrap();
+    std::fs::write(dir.join("README.md"), "Not configuration").unwrap();
+
+    let server = PartialServer::default()
+        .source(ConfDir(&dir))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "override");
+    assert_eq!(server.port, 8080);
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn inline_sources_layer_like_files() {
+    use partial_config::serde_support::{InlineJson, InlineToml};
+
+    let server = PartialServer::default()
+        .source(InlineToml("host = \"baked-in\"\nport = 80\n"))
+        .unwrap()
+        .source(InlineJson(r#"{"port": 8080}"#))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "baked-in");
+    assert_eq!(server.port, 8080);
+    assert_eq!(
+        <InlineToml as partial_config::Source<Server>>::name(&InlineToml("")),
+        "Inline TOML defaults"
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn any_deserializer_is_a_source() {
+    use partial_config::serde_support::{DeserializerSource, FileReadError};
+
+    let partial = PartialServer::default()
+        .source(DeserializerSource(toml::Deserializer::new(
+            "host = \"localhost\"\nname = \"primary\"\n",
+        )))
+        .unwrap();
+    assert_eq!(partial.host.as_deref(), Some("localhost"));
+    assert_eq!(partial.name.as_deref(), Some("primary"));
+    assert_eq!(partial.port, None);
+
+    let err = PartialServer::default()
+        .source(DeserializerSource(toml::Deserializer::new(
+            "port = \"eighty\"\n",
+        )))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        partial_config::Error::FileReadError(FileReadError::Deserialize(_))
+    ));
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn in_memory_sources_need_no_path() {
+    use partial_config::serde_support::{FileReadError, JsonReader, TomlBytes};
+    use partial_config::Source;
+
+    let server = PartialServer::default()
+        .source(TomlBytes(b"host = \"from-bytes\"\nport = 80\n".to_vec()))
+        .unwrap()
+        .source(JsonReader(std::io::Cursor::new(r#"{"port": 8080}"#)))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "from-bytes");
+    assert_eq!(server.port, 8080);
+
+    assert!(matches!(
+        <TomlBytes as Source<Server>>::to_partial(TomlBytes(vec![0xff, 0xfe])),
+        Err(FileReadError::NotUtf8(_))
+    ));
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn renaming_source_reads_foreign_keys() {
+    use partial_config::serde_support::{InlineJson, InlineToml, RenamingSource};
+
+    const RENAMES: &[(&str, &str)] = &[("listen_addr", "host"), ("listen_port", "port")];
+    let server = PartialServer::default()
+        .source(RenamingSource::new(
+            InlineToml("listen_addr = \"0.0.0.0\"\nlisten_port = 80\n"),
+            RENAMES,
+        ))
+        .unwrap()
+        .source(RenamingSource::new(
+            InlineJson(r#"{"listen_port": 8080, "name": "foreign"}"#),
+            RENAMES,
+        ))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "0.0.0.0");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.name.as_deref(), Some("foreign"));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn renaming_source_reads_files_like_the_inner_source() {
+    use partial_config::serde_support::{FileReadError, RenamingSource, Toml};
+    use partial_config::Source;
+
+    const RENAMES: &[(&str, &str)] = &[("listen_port", "port")];
+    let dir = scratch_dir("renaming_files");
+    let foreign = dir.join("foreign.toml");
+    std::fs::write(&foreign, "host = \"localhost\"\nlisten_port = 80\n").unwrap();
+    let server = PartialServer::default()
+        .source(RenamingSource::new(Toml(&foreign), RENAMES))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 80);
+
+    let bare = dir.join("bare.toml");
+    std::fs::write(&bare, "8080\n").unwrap();
+    let source = RenamingSource::new(Toml(&bare), RENAMES);
+    assert_eq!(
+        <RenamingSource<'_, Toml> as Source<Server>>::name(&source),
+        format!("TOML file at {bare:?} with renamed keys")
+    );
+    assert!(matches!(
+        <RenamingSource<'_, Toml> as Source<Server>>::to_partial(source),
+        Err(FileReadError::NotATable { path }) if path == bare
+    ));
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Tls {
+    pub cert: String,
+    pub key: String,
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Secured {
+    pub port: u16,
+    #[partial_flatten]
+    pub tls: Tls,
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn flattened_fields_are_read_from_a_table() {
+    use partial_config::serde_support::InlineToml;
+
+    let secured = PartialSecured::default()
+        .source(InlineToml("port = 443\n[tls]\ncert = \"server.pem\"\n"))
+        .unwrap()
+        .source(InlineToml("[tls]\nkey = \"server.key\"\n"))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(secured.port, 443);
+    assert_eq!(secured.tls.cert, "server.pem");
+    assert_eq!(secured.tls.key, "server.key");
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn override_source_sets_fields_by_path() {
+    use partial_config::serde_support::{InlineToml, OverrideSource};
+    use partial_config::{Error, Source};
+
+    let secured = PartialSecured::default()
+        .source(InlineToml("port = 443\n[tls]\ncert = \"server.pem\"\n"))
+        .unwrap()
+        .source(OverrideSource(vec![
+            "port=80".to_owned(),
+            "tls.key = server.key".to_owned(),
+            "port=8443".to_owned(),
+        ]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(secured.port, 8443);
+    assert_eq!(secured.tls.cert, "server.pem");
+    assert_eq!(secured.tls.key, "server.key");
+
+    for setting in ["port", "tls.=1", ".port=1"] {
+        let err = <OverrideSource as Source<Secured>>::to_partial(OverrideSource(vec![
+            setting.to_owned()
+        ]))
+        .unwrap_err();
+        assert!(
+            matches!(err, Error::InvalidOverride { .. }),
+            "{setting}: {err:?}"
+        );
+    }
+    let err = <OverrideSource as Source<Secured>>::to_partial(OverrideSource(vec![
+        "tls=1".to_owned(),
+        "tls.key=server.key".to_owned(),
+    ]))
+    .unwrap_err();
+    assert!(matches!(err, Error::InvalidOverride { .. }), "{err:?}");
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn first_of_uses_the_first_source_that_succeeds() {
+    use partial_config::serde_support::Toml;
+    use partial_config::{Error, FirstOf, Source};
+
+    let dir = scratch_dir("first_of");
+    let missing = dir.join("missing.toml");
+    let broken = dir.join("broken.toml");
+    let good = dir.join("good.toml");
+    let other = dir.join("other.toml");
+    std::fs::write(&broken, "host = ").unwrap();
+    std::fs::write(&good, "host = \"good\"\nport = 1\n").unwrap();
+    std::fs::write(&other, "host = \"other\"\nport = 2\n").unwrap();
+
+    let server = PartialServer::default()
+        .source(FirstOf(vec![
+            Box::new(Toml(&missing)),
+            Box::new(Toml(&broken)),
+            Box::new(Toml(&good)),
+            Box::new(Toml(&other)),
+        ]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "good");
+    assert_eq!(server.port, 1);
+
+    let Err(Error::NoSourceSucceeded { failures }) =
+        <FirstOf<Server> as Source<Server>>::to_partial(FirstOf(vec![
+            Box::new(Toml(&missing)),
+            Box::new(Toml(&broken)),
+        ]))
+    else {
+        panic!("All of the sources should have failed");
+    };
+    assert_eq!(failures.len(), 2);
+}
+
+/// Not `Deserialize`, so it can only be provided by a non-serde source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuntimeHandle(pub u32);
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+#[allow(deprecated)] // The handle is provided by `HandleSource`.
+pub struct Runtime {
+    pub host: String,
+    #[partial_only(serde(skip))]
+    pub handle: RuntimeHandle,
+}
+
+pub struct HandleSource(pub RuntimeHandle);
+
+impl partial_config::Source<Runtime> for HandleSource {
+    type Error = partial_config::Error;
+
+    fn to_partial(self) -> Result<PartialRuntime, Self::Error> {
+        Ok(PartialRuntime {
+            host: None,
+            handle: Some(self.0),
+        })
+    }
+
+    fn name(&self) -> String {
+        "Runtime handle".to_owned()
+    }
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn skipped_fields_are_only_populated_by_other_sources() {
+    use partial_config::serde_support::InlineToml;
+
+    let from_file = PartialRuntime::default()
+        .source(InlineToml("host = \"localhost\"\n"))
+        .unwrap();
+    assert!(from_file.handle.is_none());
+
+    let runtime = from_file
+        .source(HandleSource(RuntimeHandle(3)))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(runtime.host, "localhost");
+    assert_eq!(runtime.handle, RuntimeHandle(3));
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Backend {
+    pub name: String,
+    pub address: String,
+    pub weight: Option<u32>,
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct LoadBalancer {
+    #[partial_merge(by_key = "name")]
+    pub server: Vec<Backend>,
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn entries_are_merged_by_key_and_appended_otherwise() {
+    use partial_config::serde_support::InlineToml;
+
+    let base = r#"
+[[server]]
+name = "a"
+address = "10.0.0.1"
+
+[[server]]
+name = "b"
+address = "10.0.0.2"
+"#;
+    let overlay = r#"
+[[server]]
+name = "b"
+weight = 5
+
+[[server]]
+name = "c"
+address = "10.0.0.3"
+"#;
+    let balancer = PartialLoadBalancer::default()
+        .source(InlineToml(base))
+        .unwrap()
+        .source(InlineToml(overlay))
+        .unwrap()
+        .build()
+        .unwrap();
+    let servers: Vec<(&str, &str, Option<u32>)> = balancer
+        .server
+        .iter()
+        .map(|server| (server.name.as_str(), server.address.as_str(), server.weight))
+        .collect();
+    assert_eq!(
+        servers,
+        vec![
+            ("a", "10.0.0.1", None),
+            ("b", "10.0.0.2", Some(5)),
+            ("c", "10.0.0.3", None)
+        ]
+    );
+
+    let Err(partial_config::Error::MissingFields { required_fields }) =
+        PartialLoadBalancer::default()
+            .source(InlineToml(overlay))
+            .unwrap()
+            .build()
+    else {
+        panic!("The entry `b` has no address");
+    };
+    assert_eq!(required_fields.len(), 1);
+    assert_eq!(required_fields[0].path, "server[0].address");
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn strict_layers_reject_disagreements() {
+    use partial_config::serde_support::InlineToml;
+    use partial_config::{Error, Strict};
+
+    let server = Strict::new(PartialServer::default())
+        .source(InlineToml("host = \"localhost\"\nport = 80\n"))
+        .unwrap()
+        .source(InlineToml("port = 80\nname = \"web\"\n"))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 80);
+    assert_eq!(server.name.as_deref(), Some("web"));
+
+    let Err(Error::InconsistentSettings { conflicts }) = Strict::new(PartialServer::default())
+        .source(InlineToml("host = \"localhost\"\nport = 80\n"))
+        .unwrap()
+        .source(InlineToml("host = \"example.com\"\nport = 8080\n"))
+    else {
+        panic!("The two layers disagree on the host and the port");
+    };
+    let fields: Vec<(&str, &str, &str)> = conflicts
+        .iter()
+        .map(|conflict| {
+            (
+                conflict.field,
+                conflict.first_setting.as_str(),
+                conflict.second_setting.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("host", "\"localhost\"", "\"example.com\""),
+            ("port", "80", "8080")
+        ]
+    );
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn reset_discards_every_layer() {
+    use partial_config::serde_support::InlineToml;
+    use partial_config::Strict;
+
+    let mut partial = PartialServer::default()
+        .source(InlineToml("host = \"localhost\"\nport = 80\n"))
+        .unwrap();
+    partial.reset();
+    assert!(partial.host.is_none());
+    assert!(partial.port.is_none());
+
+    let mut strict = Strict::new(PartialServer::default())
+        .source(InlineToml("port = 80\n"))
+        .unwrap();
+    strict.reset();
+    let server = strict
+        .source(InlineToml("host = \"localhost\"\nport = 8080\n"))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 8080);
+}
+
+#[cfg(all(feature = "toml", feature = "json"))]
+#[test]
+fn parsed_values_are_sources() {
+    let server = PartialServer::default()
+        .source(toml::Value::Table(toml::toml! {
+            host = "from-toml"
+            port = 80
+        }))
+        .unwrap()
+        .source(serde_json::json!({ "port": 8080 }))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "from-toml");
+    assert_eq!(server.port, 8080);
+}
+
+#[cfg(all(unix, feature = "toml"))]
+#[test]
+fn non_unicode_extensions_are_reported() {
+    use partial_config::serde_support::FileReadError;
+    use partial_config::Source;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = scratch_dir("non_unicode").join(std::ffi::OsStr::from_bytes(b"server.\xff"));
+    std::fs::write(&path, "port = 1\n").unwrap();
+    assert!(matches!(
+        <std::path::PathBuf as Source<Server>>::to_partial(path),
+        Err(FileReadError::NonUnicodeExtension(_))
+    ));
+}
+
+#[cfg(all(feature = "toml", feature = "json", feature = "audit"))]
+#[test]
+fn logged_sources_record_every_change() {
+    use partial_config::serde_support::{InlineJson, InlineToml};
+    use partial_config::ResolutionStep;
+
+    let mut log = Vec::new();
+    let server = PartialServer::default()
+        .source_logged(InlineToml("host = \"localhost\"\nport = 80\n"), &mut log)
+        .unwrap()
+        .source_logged(InlineJson(r#"{"port": 8080}"#), &mut log)
+        .unwrap()
+        .source_logged(InlineJson(r#"{"port": 8080}"#), &mut log)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 8080);
+    assert_eq!(
+        log,
+        vec![
+            ResolutionStep {
+                source: "Inline TOML defaults".to_owned(),
+                field: "host",
+                old: None,
+                new: "\"localhost\"".to_owned(),
+            },
+            ResolutionStep {
+                source: "Inline TOML defaults".to_owned(),
+                field: "port",
+                old: None,
+                new: "80".to_owned(),
+            },
+            ResolutionStep {
+                source: "Inline JSON defaults".to_owned(),
+                field: "port",
+                old: Some("80".to_owned()),
+                new: "8080".to_owned(),
+            },
+        ]
+    );
+}
+
+#[cfg(all(feature = "humantime", feature = "bytesize"))]
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Deserialize)]
+pub struct Limits {
+    #[env_parse_duration]
+    pub timeout: std::time::Duration,
+    #[env_parse_duration]
+    pub idle: Option<std::time::Duration>,
+    #[env_parse_bytesize]
+    pub max_body: u64,
+}
+
+#[cfg(all(feature = "toml", feature = "humantime", feature = "bytesize"))]
+#[test]
+fn file_values_with_units_are_parsed() {
+    use partial_config::serde_support::InlineToml;
+    use std::time::Duration;
+
+    let limits = PartialLimits::default()
+        .source(InlineToml("timeout = \"1m 30s\"\nmax_body = \"8MiB\"\n"))
+        .unwrap()
+        .source(InlineToml("idle = 300\n"))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(limits.timeout, Duration::from_secs(90));
+    assert_eq!(limits.idle, Some(Duration::from_secs(300)));
+    assert_eq!(limits.max_body, 8 << 20);
+
+    assert!(PartialLimits::default()
+        .source(InlineToml("timeout = \"soon\"\n"))
+        .is_err());
+}
+
+#[cfg(feature = "config-compat")]
+#[test]
+fn config_crate_configuration_is_a_layer() {
+    let legacy = config::Config::builder()
+        .set_override("host", "legacy.example.com")
+        .unwrap()
+        .set_override("port", 8080)
+        .unwrap()
+        .set_override("retired", true)
+        .unwrap()
+        .build()
+        .unwrap();
+    let server = PartialServer::default()
+        .source(legacy)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "legacy.example.com");
+    assert_eq!(server.port, 8080);
+    assert_eq!(server.name, None);
+}
+
+#[cfg(all(feature = "toml", feature = "interpolate"))]
+#[test]
+fn environment_variables_are_interpolated_into_strings() {
+    use partial_config::serde_support::InlineToml;
+    use partial_config::Error;
+
+    std::env::set_var("PARTIAL_CONFIG_TEST_PKI", "/etc/pki");
+    let secured = PartialSecured::default()
+        .source(InlineToml(
+            "port = 443\n[tls]\ncert = \"${PARTIAL_CONFIG_TEST_PKI}/server.pem\"\nkey = \"$HOME/${PARTIAL_CONFIG_TEST_PKI}\"\n",
+        ))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(secured.tls.cert, "/etc/pki/server.pem");
+    assert_eq!(secured.tls.key, "$HOME//etc/pki");
+
+    let err = PartialSecured::default()
+        .source(InlineToml(
+            "[tls]\nkey = \"${PARTIAL_CONFIG_TEST_UNSET}/server.key\"\n",
+        ))
+        .unwrap_err();
+    match err {
+        Error::MissingInterpolation { var, field } => {
+            assert_eq!(var, "PARTIAL_CONFIG_TEST_UNSET");
+            assert_eq!(field, "tls.key");
+        }
+        other => panic!("{other:?}"),
+    }
+}
+
+#[derive(Debug, HasPartial, serde::Deserialize)]
+#[partial_derives(Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Listener {
+    pub listen_port: u16,
+    pub bind_address: Option<String>,
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn container_serde_attributes_apply_to_the_partial() {
+    use partial_config::serde_support::InlineToml;
+
+    let listener = PartialListener::default()
+        .source(InlineToml("listen-port = 8080\nbind-address = \"::1\"\n"))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(listener.listen_port, 8080);
+    assert_eq!(listener.bind_address.as_deref(), Some("::1"));
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn optional_files_are_read_only_if_present() {
+    use partial_config::serde_support::{FileReadError, OptionalFile};
+    use partial_config::Source;
+
+    let dir = scratch_dir("optional");
+    let partial = PartialServer::default()
+        .source(OptionalFile(&dir.join("absent.toml")))
+        .unwrap();
+    assert!(partial.host.is_none() && partial.port.is_none());
+
+    let present = dir.join("present.toml");
+    std::fs::write(&present, "host = \"localhost\"\nport = 80\n").unwrap();
+    let server = PartialServer::default()
+        .source(OptionalFile(&present))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 80);
+
+    let broken = dir.join("broken.toml");
+    std::fs::write(&broken, "port = \n").unwrap();
+    let err = <OptionalFile as Source<Server>>::to_partial(OptionalFile(&broken)).unwrap_err();
+    assert!(matches!(err, FileReadError::TomlFile { .. }), "{err:?}");
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn borrowed_paths_dispatch_on_the_extension() {
+    use partial_config::serde_support::FileReadError;
+    use partial_config::Source;
+
+    let dir = scratch_dir("borrowed");
+    let file = dir.join("server.toml");
+    std::fs::write(&file, "host = \"localhost\"\nport = 80\n").unwrap();
+    let server = PartialServer::default()
+        .source(file.as_path())
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.port, 80);
+
+    let missing = dir.join("missing.toml");
+    let err = <&std::path::Path as Source<Server>>::to_partial(&missing).unwrap_err();
+    assert!(matches!(err, FileReadError::NoFile(path) if path == missing));
+
+    let unknown = dir.join("server.ini");
+    std::fs::write(&unknown, "port = 80\n").unwrap();
+    let err = <&std::path::Path as Source<Server>>::to_partial(&unknown).unwrap_err();
+    assert!(matches!(err, FileReadError::UnsupportedExtension(ext) if ext == "ini"));
+}
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, serde::Serialize, serde::Deserialize)]
+pub struct Admin {
+    pub port: u16,
+    pub motd: Option<String>,
+    pub workers: Option<u32>,
+    #[partial_nullable]
+    pub banner: Option<String>,
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn merge_patches_set_and_clear_fields() {
+    let mut partial = PartialAdmin {
+        port: Some(80),
+        motd: Some("hello".to_owned()),
+        workers: None,
+        banner: Some(None),
+    };
+    partial
+        .apply_merge_patch(serde_json::json!({ "port": 8080, "motd": null, "workers": 4 }))
+        .unwrap();
+    assert_eq!(partial.port, Some(8080));
+    assert_eq!(partial.motd, None);
+    assert_eq!(partial.workers, Some(4));
+    assert_eq!(partial.banner, Some(None));
+
+    partial
+        .apply_merge_patch(serde_json::json!({ "banner": "welcome" }))
+        .unwrap();
+    assert_eq!(partial.banner, Some(Some("welcome".to_owned())));
+    partial
+        .apply_merge_patch(serde_json::json!({ "banner": null }))
+        .unwrap();
+    assert_eq!(partial.banner, None);
+
+    partial
+        .apply_merge_patch(serde_json::json!({ "motd": "back" }))
+        .unwrap();
+    assert_eq!(partial.port, Some(8080));
+    assert_eq!(partial.motd.as_deref(), Some("back"));
+
+    let err = partial
+        .apply_merge_patch(serde_json::json!({ "port": "eighty" }))
+        .unwrap_err();
+    assert!(matches!(err, partial_config::Error::FileReadError(_)));
+    assert_eq!(partial.port, Some(8080));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mapped_toml_files_read_like_toml_files() {
+    use partial_config::serde_support::{FileReadError, TomlMmap};
+    use partial_config::Source;
+
+    let dir = scratch_dir("mmap");
+    let file = dir.join("server.toml");
+    std::fs::write(&file, "host = \"localhost\"\nport = 80\n").unwrap();
+    let server = PartialServer::default()
+        .source(TomlMmap(&file))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 80);
+
+    let empty = dir.join("empty.toml");
+    std::fs::write(&empty, "").unwrap();
+    let partial = <TomlMmap as Source<Server>>::to_partial(TomlMmap(&empty)).unwrap();
+    assert!(partial.port.is_none());
+
+    let list = dir.join("list.toml");
+    std::fs::write(&list, "[1, 2]").unwrap();
+    let err = <TomlMmap as Source<Server>>::to_partial(TomlMmap(&list)).unwrap_err();
+    assert!(matches!(err, FileReadError::NotATable { .. }), "{err:?}");
+}
@@ -4,4 +4,8 @@ fn ui() {
     t.compile_fail("tests/ui/*.rs");
     t.pass("tests/ui/generic_lifetimes.rs");
     t.pass("tests/ui/env_option.rs");
+    t.pass("tests/ui/env_option_vec.rs");
+    t.pass("tests/ui/generic_bounds.rs");
+    t.pass("tests/ui/only_optional.rs");
+    t.pass("tests/ui/copy_fields.rs");
 }
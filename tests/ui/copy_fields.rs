@@ -0,0 +1,14 @@
+#[derive(partial_config::HasPartial)]
+#[partial_derives(Clone, Copy)]
+pub struct Limits {
+    pub port: u16,
+    pub name: &'static str,
+    #[partial_nullable]
+    pub timeout: Option<u64>,
+}
+
+fn main() {
+    let partial = PartialLimits::default();
+    let copy = partial;
+    let _ = (partial, copy);
+}
@@ -0,0 +1,9 @@
+#[derive(partial_config::HasPartial)]
+#[partial_derives(Clone, Copy)]
+pub struct Limits {
+    pub port: u16,
+    pub name: String,
+    pub tags: Option<Vec<u8>>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+pub struct Configuration {
+    #[env(PORT, SHARED)]
+    pub port: u16,
+    #[env(SHARED)]
+    pub admin_port: u16,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+pub struct Configuration {
+    #[env(VERBOSITY, flag)]
+    pub verbosity: u8,
+}
+
+fn main() {}
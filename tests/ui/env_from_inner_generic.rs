@@ -0,0 +1,12 @@
+use partial_config::{EnvSourced, HasPartial};
+
+pub struct Wrapper<T>(pub T);
+
+#[derive(HasPartial, EnvSourced)]
+pub struct Configuration {
+    #[env(PORT)]
+    #[env_from_inner]
+    pub port: Wrapper<u16>,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+pub struct Configuration {
+    #[env(PORTS)]
+    pub ports: Option<Vec<u16>>,
+    #[env(TAGS)]
+    #[env_separator(";")]
+    pub tags: Vec<String>,
+}
+
+fn main()  {}
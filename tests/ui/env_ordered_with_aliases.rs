@@ -0,0 +1,7 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+pub struct Configuration {
+    #[env(PORT, ordered(SERVICE_PORT, SHARED_PORT))]
+    pub port: u16,
+}
+
+fn main() {}
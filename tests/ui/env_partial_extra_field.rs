@@ -0,0 +1,38 @@
+use partial_config::{EnvSourced, HasPartial, Partial};
+
+#[derive(Default)]
+pub struct PartialConfiguration {
+    pub port: Option<u16>,
+}
+
+impl Partial for PartialConfiguration {
+    type Target = Configuration;
+    type Error = partial_config::Error;
+
+    fn build(self) -> Result<Configuration, Self::Error> {
+        Ok(Configuration {
+            port: self.port.unwrap_or_default(),
+            host: String::new(),
+        })
+    }
+
+    fn override_with(self, other: Self) -> Self {
+        Self {
+            port: other.port.or(self.port),
+        }
+    }
+}
+
+impl HasPartial for Configuration {
+    type Partial = PartialConfiguration;
+}
+
+#[derive(EnvSourced)]
+pub struct Configuration {
+    #[env(PORT)]
+    pub port: u16,
+    #[env(HOST)]
+    pub host: String,
+}
+
+fn main() {}
@@ -0,0 +1,37 @@
+use partial_config::{EnvSourced, HasPartial, Partial};
+
+#[derive(Default)]
+pub struct PartialConfiguration {
+    pub port: Option<u16>,
+    pub extra: Option<u8>,
+}
+
+impl Partial for PartialConfiguration {
+    type Target = Configuration;
+    type Error = partial_config::Error;
+
+    fn build(self) -> Result<Configuration, Self::Error> {
+        Ok(Configuration {
+            port: self.port.unwrap_or_default(),
+        })
+    }
+
+    fn override_with(self, other: Self) -> Self {
+        Self {
+            port: other.port.or(self.port),
+            extra: other.extra.or(self.extra),
+        }
+    }
+}
+
+impl HasPartial for Configuration {
+    type Partial = PartialConfiguration;
+}
+
+#[derive(EnvSourced)]
+pub struct Configuration {
+    #[env(PORT)]
+    pub port: u16,
+}
+
+fn main() {}
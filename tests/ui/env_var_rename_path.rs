@@ -0,0 +1,8 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+#[env_var_rename(sources::ConfigurationEnv)]
+pub struct Configuration {
+    #[env(PORT)]
+    pub port: u16,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+#[env_var_rename(FirstEnv)]
+#[env_var_rename(SecondEnv)]
+pub struct Configuration {
+    #[env(PORT)]
+    pub port: u16,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+#![deny(deprecated)]
+
+#[derive(partial_config::HasPartial)]
+pub struct Forgotten {
+    #[env(FORGOTTEN_PORT)]
+    pub port: u16,
+}
+
+#[derive(partial_config::HasPartial, partial_config::EnvSourced)]
+pub struct Remembered {
+    #[env(REMEMBERED_PORT)]
+    pub port: u16,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+#[derive(partial_config::HasPartial)]
+pub struct BoundedConfiguration<'a, T: Clone + Default>
+where
+    T: core::fmt::Debug,
+{
+    pub name: &'a str,
+    pub value: T,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+#[derive(partial_config::HasPartial)]
+pub struct Backend {
+    pub name: String,
+}
+
+#[derive(partial_config::HasPartial)]
+pub struct LoadBalancer {
+    #[partial_merge(by_key = "name")]
+    pub server: Backend,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[derive(partial_config::HasPartial)]
+pub struct Configuration {
+    #[partial_nullable]
+    pub port: u16,
+}
+
+fn main() {}
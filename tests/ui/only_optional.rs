@@ -0,0 +1,18 @@
+#![deny(warnings)]
+#![deny(clippy::all)]
+
+use partial_config::{HasPartial, Partial};
+
+#[derive(Debug, HasPartial)]
+#[partial_derives(Debug, Clone)]
+pub struct Optional {
+    pub port: Option<u16>,
+    #[partial_nullable]
+    pub host: Option<String>,
+}
+
+fn main() {
+    let optional = PartialOptional::default().build().unwrap();
+    assert_eq!(optional.port, None);
+    assert_eq!(optional.host, None);
+}
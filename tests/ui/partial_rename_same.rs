@@ -0,0 +1,7 @@
+#[derive(partial_config::HasPartial)]
+#[partial_rename(Configuration)]
+pub struct Configuration {
+    pub port: u16,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+#[derive(partial_config::HasPartial)]
+#[partial_rename(FirstLayer)]
+#[partial_rename(SecondLayer)]
+pub struct Configuration {
+    pub port: u16,
+}
+
+fn main() {}
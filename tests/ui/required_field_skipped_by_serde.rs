@@ -0,0 +1,28 @@
+#![deny(deprecated)]
+
+#[derive(partial_config::HasPartial)]
+#[partial_derives(serde::Deserialize)]
+pub struct Unreachable {
+    #[partial_only(serde(skip))]
+    pub handle: u32,
+}
+
+#[derive(partial_config::HasPartial)]
+#[partial_derives(serde::Deserialize)]
+pub struct Defaulted {
+    #[partial_only(serde(skip))]
+    #[partial_default(3)]
+    pub handle: u32,
+    #[partial_only(serde(skip))]
+    pub optional: Option<u32>,
+}
+
+#[derive(partial_config::HasPartial)]
+#[partial_derives(serde::Deserialize)]
+#[allow(deprecated)]
+pub struct Provided {
+    #[partial_only(serde(skip))]
+    pub handle: u32,
+}
+
+fn main() {}
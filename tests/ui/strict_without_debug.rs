@@ -0,0 +1,11 @@
+use partial_config::{HasPartial, Strict};
+
+// Without `Debug`, the layers cannot be compared, so `Strict` would not notice conflicts.
+#[derive(HasPartial)]
+pub struct Server {
+    pub port: u16,
+}
+
+fn main() {
+    let _ = Strict::new(PartialServer::default());
+}
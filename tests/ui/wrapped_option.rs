@@ -0,0 +1,10 @@
+use partial_config::HasPartial;
+
+#[derive(HasPartial)]
+pub struct Configuration {
+    pub name: Box<Option<String>>,
+    pub retries: std::sync::Arc<Option<u32>>,
+    pub fine: Option<Box<u32>>,
+}
+
+fn main() {}